@@ -15,7 +15,7 @@ async fn main() -> Result<()> {
         let transport = ClientStdioTransport::new("cat", &[])?;
 
         // Open transport
-        transport.open()?;
+        transport.open().await?;
 
         let client = ClientBuilder::new(transport).build();
         let client_clone = client.clone();
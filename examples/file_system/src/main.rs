@@ -1,6 +1,8 @@
 use std::path::{Path, PathBuf};
 
 use anyhow::Result;
+use mcp_sdk::context::RequestContext;
+use mcp_sdk::error::McpError;
 use mcp_sdk::server::Server;
 use mcp_sdk::transport::ServerStdioTransport;
 use mcp_sdk::types::{
@@ -17,13 +19,13 @@ async fn main() -> Result<()> {
         .with_writer(std::io::stderr)
         .init();
 
-    let server = Server::builder(ServerStdioTransport)
+    let server = Server::builder(ServerStdioTransport::default())
         .capabilities(ServerCapabilities {
             tools: Some(json!({})),
             ..Default::default()
         })
         .request_handler("tools/list", list_tools)
-        .request_handler("tools/call", call_tool)
+        .context_request_handler("tools/call", call_tool)
         .request_handler("resources/list", |_req: ListRequest| {
             Ok(ResourcesListResponse {
                 resources: vec![],
@@ -43,17 +45,21 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-fn call_tool(req: CallToolRequest) -> Result<CallToolResponse> {
+async fn call_tool(
+    req: CallToolRequest,
+    ctx: RequestContext,
+) -> mcp_sdk::error::Result<CallToolResponse> {
     let name = req.name.as_str();
     let args = req.arguments.unwrap_or_default();
+    let allowed = allowed_directories(&ctx).await?;
     let result = match name {
         "read_file" => {
-            let path = get_path(&args)?;
+            let path = get_path(&args, &allowed)?;
             let content = std::fs::read_to_string(path)?;
             ToolResponseContent::Text { text: content }
         }
         "list_directory" => {
-            let path = get_path(&args)?;
+            let path = get_path(&args, &allowed)?;
             let entries = std::fs::read_dir(path)?;
             let mut text = String::new();
             for entry in entries {
@@ -71,7 +77,7 @@ fn call_tool(req: CallToolRequest) -> Result<CallToolResponse> {
             ToolResponseContent::Text { text }
         }
         "search_files" => {
-            let path = get_path(&args)?;
+            let path = get_path(&args, &allowed)?;
             let pattern = args["pattern"].as_str().unwrap();
             let mut matches = Vec::new();
             search_directory(&path, pattern, &mut matches)?;
@@ -80,16 +86,21 @@ fn call_tool(req: CallToolRequest) -> Result<CallToolResponse> {
             }
         }
         "get_file_info" => {
-            let path = get_path(&args)?;
+            let path = get_path(&args, &allowed)?;
             let metadata = std::fs::metadata(path)?;
             ToolResponseContent::Text {
                 text: format!("{:?}", metadata),
             }
         }
         "list_allowed_directories" => ToolResponseContent::Text {
-            text: "[]".to_string(),
+            text: json!(allowed).to_string(),
         },
-        _ => return Err(anyhow::anyhow!("Unknown tool: {}", req.name)),
+        _ => {
+            return Err(McpError::invalid_params(format!(
+                "Unknown tool: {}",
+                req.name
+            )))
+        }
     };
     Ok(CallToolResponse {
         content: vec![result],
@@ -113,30 +124,50 @@ fn search_directory(dir: &Path, pattern: &str, matches: &mut Vec<String>) -> Res
             matches.push(path.to_string_lossy().to_string());
         }
 
-        // Recursively search subdirectories
-        if path.is_dir() {
+        // Recursively search subdirectories, without following symlinks out of them
+        if entry.file_type()?.is_dir() {
             search_directory(&path, pattern, matches)?;
         }
     }
     Ok(())
 }
 
-fn get_path(args: &serde_json::Value) -> Result<PathBuf> {
+/// The directories the tools may access, the roots of the client
+async fn allowed_directories(ctx: &RequestContext) -> mcp_sdk::error::Result<Vec<PathBuf>> {
+    let roots = ctx.list_roots().await?;
+    Ok(roots
+        .iter()
+        .filter_map(|root| root.uri.to_file_path().ok())
+        .filter_map(|path| path.canonicalize().ok())
+        .collect())
+}
+
+/// Resolve the `path` argument, it must be within one of the `allowed` directories
+fn get_path(args: &serde_json::Value, allowed: &[PathBuf]) -> mcp_sdk::error::Result<PathBuf> {
     let path = args["path"]
         .as_str()
-        .ok_or(anyhow::anyhow!("Missing path"))?;
+        .ok_or(McpError::invalid_params("Missing path"))?;
 
-    if path.starts_with('~') {
-        let home = home::home_dir().ok_or(anyhow::anyhow!("Could not determine home directory"))?;
+    let path = if path.starts_with('~') {
+        let home =
+            home::home_dir().ok_or(McpError::internal("Could not determine home directory"))?;
         // Strip the ~ and join with home path
-        let path = home.join(path.strip_prefix("~/").unwrap_or_default());
-        Ok(path)
+        home.join(path.strip_prefix("~/").unwrap_or_default())
     } else {
-        Ok(PathBuf::from(path))
+        PathBuf::from(path)
+    };
+    // resolve `..` and symlinks before checking the path
+    let path = path.canonicalize()?;
+    if !allowed.iter().any(|directory| path.starts_with(directory)) {
+        return Err(McpError::invalid_params(format!(
+            "Access denied: {} is outside the allowed directories",
+            path.display()
+        )));
     }
+    Ok(path)
 }
 
-fn list_tools(_req: ListRequest) -> Result<ToolsListResponse> {
+fn list_tools(_req: ListRequest) -> mcp_sdk::error::Result<ToolsListResponse> {
     let response = json!({
       "tools": [
         {
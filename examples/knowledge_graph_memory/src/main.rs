@@ -19,7 +19,7 @@ async fn main() -> Result<()> {
     let kg = Arc::new(Mutex::new(kg));
     let tools = tool_set::tool_set(kg, memory_file_path.to_string());
 
-    let server = Server::builder(ServerStdioTransport)
+    let server = Server::builder(ServerStdioTransport::default())
         .capabilities(ServerCapabilities {
             tools: Some(json!({})),
             ..Default::default()
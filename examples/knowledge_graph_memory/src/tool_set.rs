@@ -2,7 +2,9 @@ use crate::types::{
     AddObservationParams, DeleteObservationParams, Entity, KnowledgeGraph, Relation,
 };
 use anyhow::Result;
+use async_trait::async_trait;
 use mcp_sdk::{
+    context::RequestContext,
     tools::{Tool, Tools},
     types::{CallToolResponse, ToolResponseContent},
 };
@@ -55,6 +57,7 @@ impl CreateEntitiesTool {
     }
 }
 
+#[async_trait]
 impl Tool for CreateEntitiesTool {
     fn name(&self) -> String {
         "create_entities".to_string()
@@ -87,7 +90,11 @@ impl Tool for CreateEntitiesTool {
         })
     }
 
-    fn call(&self, input: Option<serde_json::Value>) -> Result<CallToolResponse> {
+    async fn call(
+        &self,
+        input: Option<serde_json::Value>,
+        _ctx: RequestContext,
+    ) -> Result<CallToolResponse> {
         let args = input.unwrap_or_default();
         let entities = args
             .get("entities")
@@ -123,6 +130,7 @@ impl CreateRelationsTool {
     }
 }
 
+#[async_trait]
 impl Tool for CreateRelationsTool {
     fn name(&self) -> String {
         "create_relations".to_string()
@@ -153,7 +161,11 @@ impl Tool for CreateRelationsTool {
         })
     }
 
-    fn call(&self, input: Option<serde_json::Value>) -> Result<CallToolResponse> {
+    async fn call(
+        &self,
+        input: Option<serde_json::Value>,
+        _ctx: RequestContext,
+    ) -> Result<CallToolResponse> {
         let args = input.unwrap_or_default();
         let relations = args
             .get("relations")
@@ -187,6 +199,7 @@ impl ReadGraphTool {
     }
 }
 
+#[async_trait]
 impl Tool for ReadGraphTool {
     fn name(&self) -> String {
         "read_graph".to_string()
@@ -203,7 +216,11 @@ impl Tool for ReadGraphTool {
         })
     }
 
-    fn call(&self, _input: Option<serde_json::Value>) -> Result<CallToolResponse> {
+    async fn call(
+        &self,
+        _input: Option<serde_json::Value>,
+        _ctx: RequestContext,
+    ) -> Result<CallToolResponse> {
         Ok(CallToolResponse {
             content: vec![ToolResponseContent::Text {
                 text: json!(*self.kg.lock().unwrap()).to_string(),
@@ -228,6 +245,7 @@ impl AddObservationsTool {
     }
 }
 
+#[async_trait]
 impl Tool for AddObservationsTool {
     fn name(&self) -> String {
         "add_observations".to_string()
@@ -260,7 +278,11 @@ impl Tool for AddObservationsTool {
         })
     }
 
-    fn call(&self, input: Option<serde_json::Value>) -> Result<CallToolResponse> {
+    async fn call(
+        &self,
+        input: Option<serde_json::Value>,
+        _ctx: RequestContext,
+    ) -> Result<CallToolResponse> {
         let args = input.unwrap_or_default();
         let observations = args
             .get("observations")
@@ -295,6 +317,7 @@ impl DeleteEntitiesTool {
     }
 }
 
+#[async_trait]
 impl Tool for DeleteEntitiesTool {
     fn name(&self) -> String {
         "delete_entities".to_string()
@@ -317,7 +340,11 @@ impl Tool for DeleteEntitiesTool {
         })
     }
 
-    fn call(&self, input: Option<serde_json::Value>) -> Result<CallToolResponse> {
+    async fn call(
+        &self,
+        input: Option<serde_json::Value>,
+        _ctx: RequestContext,
+    ) -> Result<CallToolResponse> {
         let args = input.unwrap_or_default();
         let entity_names = args
             .get("entityNames")
@@ -350,6 +377,7 @@ impl DeleteObservationsTool {
     }
 }
 
+#[async_trait]
 impl Tool for DeleteObservationsTool {
     fn name(&self) -> String {
         "delete_observations".to_string()
@@ -382,7 +410,11 @@ impl Tool for DeleteObservationsTool {
         })
     }
 
-    fn call(&self, input: Option<serde_json::Value>) -> Result<CallToolResponse> {
+    async fn call(
+        &self,
+        input: Option<serde_json::Value>,
+        _ctx: RequestContext,
+    ) -> Result<CallToolResponse> {
         let args = input.unwrap_or_default();
         let deletions = args
             .get("deletions")
@@ -415,6 +447,7 @@ impl DeleteRelationsTool {
     }
 }
 
+#[async_trait]
 impl Tool for DeleteRelationsTool {
     fn name(&self) -> String {
         "delete_relations".to_string()
@@ -445,7 +478,11 @@ impl Tool for DeleteRelationsTool {
         })
     }
 
-    fn call(&self, input: Option<serde_json::Value>) -> Result<CallToolResponse> {
+    async fn call(
+        &self,
+        input: Option<serde_json::Value>,
+        _ctx: RequestContext,
+    ) -> Result<CallToolResponse> {
         let args = input.unwrap_or_default();
         let relations = args
             .get("relations")
@@ -474,6 +511,7 @@ impl SearchNodesTool {
     }
 }
 
+#[async_trait]
 impl Tool for SearchNodesTool {
     fn name(&self) -> String {
         "search_nodes".to_string()
@@ -493,7 +531,11 @@ impl Tool for SearchNodesTool {
         })
     }
 
-    fn call(&self, input: Option<serde_json::Value>) -> Result<CallToolResponse> {
+    async fn call(
+        &self,
+        input: Option<serde_json::Value>,
+        _ctx: RequestContext,
+    ) -> Result<CallToolResponse> {
         let args = input.unwrap_or_default();
         let query = args
             .get("query")
@@ -521,6 +563,7 @@ impl OpenNodesTool {
     }
 }
 
+#[async_trait]
 impl Tool for OpenNodesTool {
     fn name(&self) -> String {
         "open_nodes".to_string()
@@ -543,7 +586,11 @@ impl Tool for OpenNodesTool {
         })
     }
 
-    fn call(&self, input: Option<serde_json::Value>) -> Result<CallToolResponse> {
+    async fn call(
+        &self,
+        input: Option<serde_json::Value>,
+        _ctx: RequestContext,
+    ) -> Result<CallToolResponse> {
         let args = input.unwrap_or_default();
         let names = args
             .get("names")
@@ -1,18 +1,43 @@
 use crate::{
+    elicitation::ElicitationHandler,
+    error::{McpError, Result},
     protocol::{Protocol, ProtocolBuilder, RequestOptions},
+    sampling::SamplingHandler,
     transport::Transport,
     types::{
-        ClientCapabilities, Implementation, InitializeRequest, InitializeResponse,
-        LATEST_PROTOCOL_VERSION,
+        CallToolRequest, CallToolResponse, ClientCapabilities, CompleteRequest, CompleteResult,
+        CompletionArgument, CompletionReference, CreateMessageRequest, ElicitRequest,
+        GetPromptRequest, GetPromptResult, Implementation, InitializeRequest, InitializeResponse,
+        LATEST_PROTOCOL_VERSION, ListRequest, ListResourceTemplatesResponse, ListRootsResult,
+        LoggingLevel, LoggingMessageNotification, PromptsListResponse, ReadResourceRequest,
+        ReadResourceResponse, ResourcesListResponse, Root, RootCapabilities,
+        SUPPORTED_PROTOCOL_VERSIONS, SetLevelRequest, SubscribeRequest, ToolsListResponse,
     },
 };
 
-use anyhow::{Context, Result};
-use tracing::debug;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{debug, warn};
+use url::Url;
 
 #[derive(Clone)]
 pub struct Client<T: Transport> {
     protocol: Protocol<T>,
+    capabilities: ClientCapabilities,
+    /// roots answered to `roots/list`, see [`ClientBuilder::roots`]
+    roots: Arc<RwLock<Vec<Root>>>,
+    keepalive: Option<KeepAlive>,
+    alive: Arc<AtomicBool>,
+}
+
+/// Ping the server on an interval, the connection is dead after `max_missed` pings in a row fail
+#[derive(Clone, Copy)]
+struct KeepAlive {
+    interval: Duration,
+    max_missed: u32,
 }
 
 impl<T: Transport> Client<T> {
@@ -23,27 +48,26 @@ impl<T: Transport> Client<T> {
     pub async fn initialize(&self, client_info: Implementation) -> Result<InitializeResponse> {
         let request = InitializeRequest {
             protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
-            capabilities: ClientCapabilities::default(),
+            capabilities: self.capabilities.clone(),
             client_info,
         };
 
-        let request_value =
-            serde_json::to_value(request).context("Failed to serialize initialize request")?;
-
         let response = self
-            .request("initialize", Some(request_value), RequestOptions::default())
-            .await
-            .context("Failed to send initialize request")?;
-
-        let response: InitializeResponse =
-            serde_json::from_value(response).context("Failed to parse initialize response")?;
+            .request(
+                "initialize",
+                Some(serde_json::to_value(request)?),
+                RequestOptions::default(),
+            )
+            .await?;
+        let response: InitializeResponse = parse_response("initialize", response)?;
 
-        if response.protocol_version != LATEST_PROTOCOL_VERSION {
-            return Err(anyhow::anyhow!(
-                "Unsupported protocol version: expected {}, got {}",
-                LATEST_PROTOCOL_VERSION,
-                response.protocol_version
-            ));
+        // the server may answer with an older version than requested
+        if !SUPPORTED_PROTOCOL_VERSIONS.contains(&response.protocol_version.as_str()) {
+            return Err(McpError::Protocol(format!(
+                "Unsupported protocol version: {}, supported are {}",
+                response.protocol_version,
+                SUPPORTED_PROTOCOL_VERSIONS.join(", ")
+            )));
         }
 
         debug!(
@@ -53,56 +77,685 @@ impl<T: Transport> Client<T> {
 
         self.protocol
             .notify("notifications/initialized", None)
-            .context("Failed to send initialized notification")?;
+            .await?;
 
         Ok(response)
     }
 
+    /// Send a request and wait for its result
+    /// an error response from the server is returned as [`McpError::Rpc`]
     pub async fn request(
         &self,
         method: &str,
         params: Option<serde_json::Value>,
         options: RequestOptions,
     ) -> Result<serde_json::Value> {
-        let response = self
-            .protocol
+        self.protocol
             .request(method, params, options)
-            .await
-            .with_context(|| format!("Request failed for method: {}", method))?;
+            .await?
+            .into_result()
+    }
 
-        response.result.ok_or_else(|| {
-            let error_msg = response
-                .error
-                .as_ref()
-                .map(|e| format!("{}: {}", e.code, e.message))
-                .unwrap_or_else(|| "Unknown error".to_string());
+    pub async fn list_tools(&self, cursor: Option<String>) -> Result<ToolsListResponse> {
+        let request = ListRequest { cursor, meta: None };
+        let response = self
+            .request(
+                "tools/list",
+                Some(serde_json::to_value(request)?),
+                RequestOptions::default(),
+            )
+            .await?;
+        parse_response("tools/list", response)
+    }
 
-            anyhow::anyhow!("Request '{}' failed: {}", method, error_msg)
-        })
+    /// Call a tool on the server
+    /// use `RequestOptions::on_progress` to receive progress while the tool runs
+    pub async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Option<serde_json::Value>,
+        options: RequestOptions,
+    ) -> Result<CallToolResponse> {
+        let request = CallToolRequest {
+            name: name.to_string(),
+            arguments,
+            meta: None,
+        };
+        let response = self
+            .request("tools/call", Some(serde_json::to_value(request)?), options)
+            .await?;
+        parse_response("tools/call", response)
     }
 
-    pub async fn start(&self) -> Result<()> {
+    pub async fn list_resources(&self, cursor: Option<String>) -> Result<ResourcesListResponse> {
+        let request = ListRequest { cursor, meta: None };
+        let response = self
+            .request(
+                "resources/list",
+                Some(serde_json::to_value(request)?),
+                RequestOptions::default(),
+            )
+            .await?;
+        parse_response("resources/list", response)
+    }
+
+    pub async fn list_resource_templates(
+        &self,
+        cursor: Option<String>,
+    ) -> Result<ListResourceTemplatesResponse> {
+        let request = ListRequest { cursor, meta: None };
+        let response = self
+            .request(
+                "resources/templates/list",
+                Some(serde_json::to_value(request)?),
+                RequestOptions::default(),
+            )
+            .await?;
+        parse_response("resources/templates/list", response)
+    }
+
+    pub async fn read_resource(&self, uri: Url) -> Result<ReadResourceResponse> {
+        let request = ReadResourceRequest { uri, meta: None };
+        let response = self
+            .request(
+                "resources/read",
+                Some(serde_json::to_value(request)?),
+                RequestOptions::default(),
+            )
+            .await?;
+        parse_response("resources/read", response)
+    }
+
+    /// Ask the server to send `notifications/resources/updated` when `uri` changes
+    pub async fn subscribe_resource(&self, uri: Url) -> Result<()> {
+        let request = SubscribeRequest { uri };
+        self.request(
+            "resources/subscribe",
+            Some(serde_json::to_value(request)?),
+            RequestOptions::default(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn unsubscribe_resource(&self, uri: Url) -> Result<()> {
+        let request = SubscribeRequest { uri };
+        self.request(
+            "resources/unsubscribe",
+            Some(serde_json::to_value(request)?),
+            RequestOptions::default(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list_prompts(&self, cursor: Option<String>) -> Result<PromptsListResponse> {
+        let request = ListRequest { cursor, meta: None };
+        let response = self
+            .request(
+                "prompts/list",
+                Some(serde_json::to_value(request)?),
+                RequestOptions::default(),
+            )
+            .await?;
+        parse_response("prompts/list", response)
+    }
+
+    pub async fn get_prompt(
+        &self,
+        name: &str,
+        arguments: Option<HashMap<String, String>>,
+    ) -> Result<GetPromptResult> {
+        let request = GetPromptRequest {
+            name: name.to_string(),
+            arguments,
+            meta: None,
+        };
+        let response = self
+            .request(
+                "prompts/get",
+                Some(serde_json::to_value(request)?),
+                RequestOptions::default(),
+            )
+            .await?;
+        parse_response("prompts/get", response)
+    }
+
+    /// Ask the server for suggestions for a prompt argument or resource template variable
+    pub async fn complete(
+        &self,
+        reference: CompletionReference,
+        argument: CompletionArgument,
+    ) -> Result<CompleteResult> {
+        let request = CompleteRequest {
+            reference,
+            argument,
+            meta: None,
+        };
+        let response = self
+            .request(
+                "completion/complete",
+                Some(serde_json::to_value(request)?),
+                RequestOptions::default(),
+            )
+            .await?;
+        parse_response("completion/complete", response)
+    }
+
+    /// Ask the server to only send log messages at `level` or above
+    pub async fn set_log_level(&self, level: LoggingLevel) -> Result<()> {
+        let request = SetLevelRequest { level };
+        self.request(
+            "logging/setLevel",
+            Some(serde_json::to_value(request)?),
+            RequestOptions::default(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Replace the roots exposed to the server and send `notifications/roots/list_changed`
+    /// fails unless roots were enabled with [`ClientBuilder::roots`]
+    pub async fn set_roots(&self, roots: Vec<Root>) -> Result<()> {
+        if self.capabilities.roots.is_none() {
+            return Err(McpError::Protocol(
+                "Roots are not enabled, see ClientBuilder::roots".to_string(),
+            ));
+        }
+        *self.roots.write().unwrap() = roots;
         self.protocol
-            .listen()
+            .notify("notifications/roots/list_changed", None)
             .await
-            .context("Client protocol listener failed")
     }
+
+    /// Check the server is responsive
+    pub async fn ping(&self) -> Result<()> {
+        self.request("ping", None, RequestOptions::default())
+            .await?;
+        Ok(())
+    }
+
+    /// False once [`Client::start`] returned, the transport closed
+    /// or keepalive pings went unanswered, see [`ClientBuilder::keepalive`]
+    pub fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::SeqCst)
+    }
+
+    /// Listen for messages from the server
+    /// returns an error if keepalive is enabled and the server stops answering pings
+    pub async fn start(&self) -> Result<()> {
+        let listen = self.protocol.listen();
+        let result = match self.keepalive {
+            Some(keepalive) => tokio::select! {
+                result = listen => result,
+                result = self.run_keepalive(keepalive) => result,
+            },
+            None => listen.await,
+        };
+        self.alive.store(false, Ordering::SeqCst);
+        result
+    }
+
+    async fn run_keepalive(&self, keepalive: KeepAlive) -> Result<()> {
+        let mut missed = 0;
+        let mut interval = tokio::time::interval(keepalive.interval);
+        loop {
+            interval.tick().await;
+            let options = RequestOptions::default().timeout(keepalive.interval);
+            match self.request("ping", None, options).await {
+                Ok(_) => missed = 0,
+                Err(e) => {
+                    missed += 1;
+                    warn!(
+                        "Keepalive ping failed ({missed}/{}): {e}",
+                        keepalive.max_missed
+                    );
+                }
+            }
+            if missed >= keepalive.max_missed {
+                warn!("Connection dead: {missed} keepalive pings went unanswered");
+                return Err(McpError::ConnectionClosed);
+            }
+        }
+    }
+}
+
+fn parse_response<R: DeserializeOwned>(method: &str, response: serde_json::Value) -> Result<R> {
+    serde_json::from_value(response)
+        .map_err(|e| McpError::Protocol(format!("Invalid {method} response: {e}")))
 }
 
 pub struct ClientBuilder<T: Transport> {
     protocol: ProtocolBuilder<T>,
+    capabilities: ClientCapabilities,
+    roots: Arc<RwLock<Vec<Root>>>,
+    keepalive: Option<KeepAlive>,
 }
 
 impl<T: Transport> ClientBuilder<T> {
     pub fn new(transport: T) -> Self {
         Self {
             protocol: ProtocolBuilder::new(transport),
+            capabilities: ClientCapabilities::default(),
+            roots: Arc::new(RwLock::new(Vec::new())),
+            keepalive: None,
         }
     }
 
+    /// Capabilities sent to the server in `initialize`
+    /// handlers registered on the builder add their own capability, before or after this call
+    pub fn capabilities(mut self, capabilities: ClientCapabilities) -> Self {
+        let handlers = std::mem::replace(&mut self.capabilities, capabilities);
+        self.capabilities.roots = self.capabilities.roots.take().or(handlers.roots);
+        self.capabilities.sampling = self.capabilities.sampling.take().or(handlers.sampling);
+        self.capabilities.elicitation = self
+            .capabilities
+            .elicitation
+            .take()
+            .or(handlers.elicitation);
+        self
+    }
+
+    /// Answer `roots/list` requests with `roots` and advertise the roots capability
+    /// update them later with [`Client::set_roots`]
+    pub fn roots(mut self, roots: Vec<Root>) -> Self {
+        *self.roots.write().unwrap() = roots;
+        let shared = self.roots.clone();
+        self.protocol = self
+            .protocol
+            .request_handler("roots/list", move |_: serde_json::Value| {
+                Ok(ListRootsResult {
+                    roots: shared.read().unwrap().clone(),
+                    meta: None,
+                })
+            });
+        self.capabilities.roots = Some(RootCapabilities {
+            list_changed: Some(true),
+        });
+        self
+    }
+
+    /// Answer `elicitation/create` requests from the server with `handler`
+    /// and advertise the elicitation capability
+    pub fn elicitation_handler(mut self, handler: impl ElicitationHandler) -> Self {
+        let handler = Arc::new(handler);
+        self.protocol = self.protocol.context_request_handler(
+            "elicitation/create",
+            move |req: ElicitRequest, ctx| {
+                let handler = handler.clone();
+                async move { handler.elicit(req, ctx).await }
+            },
+        );
+        self.capabilities
+            .elicitation
+            .get_or_insert_with(|| serde_json::json!({}));
+        self
+    }
+
+    /// Answer `sampling/createMessage` requests from the server with `handler`
+    /// and advertise the sampling capability
+    pub fn sampling_handler(mut self, handler: impl SamplingHandler) -> Self {
+        let handler = Arc::new(handler);
+        self.protocol = self.protocol.context_request_handler(
+            "sampling/createMessage",
+            move |req: CreateMessageRequest, ctx| {
+                let handler = handler.clone();
+                async move { handler.create_message(req, ctx).await }
+            },
+        );
+        self.capabilities
+            .sampling
+            .get_or_insert_with(|| serde_json::json!({}));
+        self
+    }
+
+    /// Ping the server every `interval` while [`Client::start`] runs,
+    /// the connection is marked dead after `max_missed` consecutive pings fail
+    pub fn keepalive(mut self, interval: Duration, max_missed: u32) -> Self {
+        self.keepalive = Some(KeepAlive {
+            interval,
+            max_missed: max_missed.max(1),
+        });
+        self
+    }
+
+    /// Call `handler` with each log message sent by the server
+    pub fn on_log_message(
+        mut self,
+        handler: impl Fn(LoggingMessageNotification) + Send + Sync + 'static,
+    ) -> Self {
+        self.protocol = self.protocol.notification_handler(
+            "notifications/message",
+            move |message: LoggingMessageNotification| {
+                handler(message);
+                Ok(())
+            },
+        );
+        self
+    }
+
+    /// Set the maximum number of requests from the server handled concurrently
+    pub fn max_concurrent_requests(mut self, max: usize) -> Self {
+        self.protocol = self.protocol.max_concurrent_requests(max);
+        self
+    }
+
     pub fn build(self) -> Client<T> {
         Client {
             protocol: self.protocol.build(),
+            capabilities: self.capabilities,
+            roots: self.roots,
+            keepalive: self.keepalive,
+            alive: Arc::new(AtomicBool::new(true)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::RequestContext;
+    use crate::resources::Resources;
+    use crate::server::Server;
+    use crate::transport::{ClientStdioTransport, JsonRpcMessage, memory};
+    use crate::types::{
+        CreateMessageResult, ElicitAction, ElicitResult, ElicitationSchema, PrimitiveSchema,
+        ResourceCapabilities, Role, SamplingMessage, ServerCapabilities, ToolResponseContent,
+    };
+    use async_trait::async_trait;
+    use tokio::sync::mpsc;
+
+    /// Answers every sampling request with a canned message
+    struct FakeModel;
+
+    #[async_trait]
+    impl SamplingHandler for FakeModel {
+        async fn create_message(
+            &self,
+            request: CreateMessageRequest,
+            _ctx: RequestContext,
+        ) -> Result<CreateMessageResult> {
+            Ok(CreateMessageResult {
+                role: Role::Assistant,
+                content: ToolResponseContent::Text {
+                    text: format!("{} messages", request.messages.len()),
+                },
+                model: "fake".to_string(),
+                stop_reason: Some("endTurn".to_string()),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_roots() -> Result<()> {
+        let (client_transport, server_transport) = memory::pair();
+        let (changed_tx, mut changed_rx) = mpsc::unbounded_channel();
+        let server = Server::builder(server_transport)
+            .on_roots_list_changed(move |ctx| {
+                let changed_tx = changed_tx.clone();
+                async move {
+                    changed_tx
+                        .send(ctx.list_roots().await?)
+                        .map_err(anyhow::Error::from)?;
+                    Ok(())
+                }
+            })
+            .build();
+        let root = |path: &str| Root {
+            uri: Url::parse(path).unwrap(),
+            name: None,
+        };
+        let client = Client::builder(client_transport)
+            .roots(vec![root("file:///workspace")])
+            .build();
+        tokio::spawn({
+            let server = server.clone();
+            async move { server.listen().await }
+        });
+        tokio::spawn({
+            let client = client.clone();
+            async move { client.start().await }
+        });
+        client.initialize(Implementation::default()).await?;
+        assert_eq!(server.list_roots().await?, vec![root("file:///workspace")]);
+
+        client.set_roots(vec![root("file:///other")]).await?;
+        assert_eq!(changed_rx.recv().await, Some(vec![root("file:///other")]));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_protocol_version_negotiation() -> Result<()> {
+        let (client_transport, server_transport) = memory::pair();
+        let server = Server::builder(server_transport).build();
+        let client = Client::builder(client_transport).build();
+        tokio::spawn(async move { server.listen().await });
+        tokio::spawn({
+            let client = client.clone();
+            async move { client.start().await }
+        });
+
+        let initialize = |version: &str| {
+            let request = InitializeRequest {
+                protocol_version: version.to_string(),
+                ..Default::default()
+            };
+            client.request(
+                "initialize",
+                Some(serde_json::to_value(request).unwrap()),
+                RequestOptions::default(),
+            )
+        };
+        // a supported older version is kept, an unknown one gets the latest
+        let result = initialize("2024-11-05").await?;
+        assert_eq!(result["protocolVersion"], "2024-11-05");
+        let result = initialize("1999-01-01").await?;
+        assert_eq!(result["protocolVersion"], LATEST_PROTOCOL_VERSION);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resources_capability() -> Result<()> {
+        let (client_transport, server_transport) = memory::pair();
+        let server = Server::builder(server_transport)
+            .resources(Resources::default())
+            .build();
+        let client = Client::builder(client_transport).build();
+        tokio::spawn(async move { server.listen().await });
+        tokio::spawn({
+            let client = client.clone();
+            async move { client.start().await }
+        });
+
+        let response = client.initialize(Implementation::default()).await?;
+        let resources = response.capabilities.resources.unwrap();
+        assert_eq!(resources.subscribe, Some(true));
+        assert_eq!(resources.list_changed, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_capabilities_keep_handler_capabilities() {
+        let (transport, _) = memory::pair();
+        let client = Client::builder(transport)
+            .roots(vec![])
+            .sampling_handler(FakeModel)
+            .capabilities(ClientCapabilities {
+                experimental: Some(serde_json::json!({"feature": {}})),
+                ..Default::default()
+            })
+            .elicitation_handler(ConfirmDeploy)
+            .build();
+        let capabilities = &client.capabilities;
+        assert!(capabilities.experimental.is_some());
+        assert!(capabilities.roots.is_some());
+        assert!(capabilities.sampling.is_some());
+        assert!(capabilities.elicitation.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_resource_list_changed_requires_capability() -> Result<()> {
+        for list_changed in [None, Some(true)] {
+            let (client_transport, server_transport) = memory::pair();
+            let capabilities = ServerCapabilities {
+                resources: Some(ResourceCapabilities {
+                    list_changed,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            let server = Server::builder(server_transport)
+                .capabilities(capabilities)
+                .resources(Resources::default())
+                .build();
+            server.notify_resource_list_changed().await?;
+            server
+                .logger()
+                .log(LoggingLevel::Info, "done".into())
+                .await?;
+
+            let Some(JsonRpcMessage::Notification(notification)) =
+                client_transport.receive().await?
+            else {
+                panic!("expected a notification");
+            };
+            let expected = match list_changed {
+                Some(true) => "notifications/resources/list_changed",
+                _ => "notifications/message",
+            };
+            assert_eq!(notification.method, expected);
+        }
+        Ok(())
+    }
+
+    /// Accepts every elicitation with a fixed environment
+    struct ConfirmDeploy;
+
+    #[async_trait]
+    impl ElicitationHandler for ConfirmDeploy {
+        async fn elicit(
+            &self,
+            _request: ElicitRequest,
+            _ctx: RequestContext,
+        ) -> Result<ElicitResult> {
+            Ok(ElicitResult {
+                action: ElicitAction::Accept,
+                content: serde_json::json!({"env": "staging"}).as_object().cloned(),
+            })
         }
     }
+
+    #[tokio::test]
+    async fn test_tool_call_elicits_input() -> Result<()> {
+        let (client_transport, server_transport) = memory::pair();
+        let server = Server::builder(server_transport)
+            .context_request_handler("deploy", |_: (), ctx: RequestContext| async move {
+                let request = ElicitRequest {
+                    message: "Where to deploy?".to_string(),
+                    requested_schema: ElicitationSchema::new()
+                        .required_property("env", PrimitiveSchema::string("Environment")),
+                };
+                let result = ctx.elicit(request, RequestOptions::default()).await?;
+                Ok(result.content.unwrap()["env"].clone())
+            })
+            .build();
+        let client = Client::builder(client_transport)
+            .elicitation_handler(ConfirmDeploy)
+            .build();
+        tokio::spawn(async move { server.listen().await });
+        tokio::spawn({
+            let client = client.clone();
+            async move { client.start().await }
+        });
+
+        // the client capabilities are only known once initialized
+        let error = client
+            .request("deploy", None, RequestOptions::default())
+            .await
+            .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Client does not support elicitation")
+        );
+
+        client.initialize(Implementation::default()).await?;
+        let env = client
+            .request("deploy", None, RequestOptions::default())
+            .await?;
+        assert_eq!(env, serde_json::json!("staging"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_server_samples_client() -> Result<()> {
+        let (client_transport, server_transport) = memory::pair();
+        let server = Server::builder(server_transport).build();
+        let client = Client::builder(client_transport)
+            .sampling_handler(FakeModel)
+            .build();
+        tokio::spawn({
+            let server = server.clone();
+            async move { server.listen().await }
+        });
+        tokio::spawn({
+            let client = client.clone();
+            async move { client.start().await }
+        });
+        client.initialize(Implementation::default()).await?;
+
+        let request = CreateMessageRequest {
+            messages: vec![SamplingMessage {
+                role: Role::User,
+                content: ToolResponseContent::Text {
+                    text: "hello".to_string(),
+                },
+            }],
+            max_tokens: 100,
+            ..Default::default()
+        };
+        let result = server
+            .create_message(request, RequestOptions::default())
+            .await?;
+        assert_eq!(result.model, "fake");
+        let ToolResponseContent::Text { text } = result.content else {
+            panic!("Expected text content");
+        };
+        assert_eq!(text, "1 messages");
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn test_keepalive_detects_hung_server() -> Result<()> {
+        // `sleep` never answers, like a hung child process
+        let transport = ClientStdioTransport::new("sleep", &["10"])?;
+        transport.open().await?;
+        let client = Client::builder(transport.clone())
+            .keepalive(Duration::from_millis(20), 2)
+            .build();
+
+        assert!(client.is_alive());
+        assert!(client.start().await.is_err());
+        assert!(!client.is_alive());
+        transport.close().await?;
+
+        // a closed transport ends the connection as well
+        let (client_transport, server_transport) = memory::pair();
+        let server = Server::builder(server_transport).build();
+        tokio::spawn(async move { server.listen().await });
+        let client = Client::builder(client_transport.clone())
+            .keepalive(Duration::from_secs(60), 2)
+            .build();
+        let start = tokio::spawn({
+            let client = client.clone();
+            async move { client.start().await }
+        });
+        client.initialize(Implementation::default()).await?;
+        assert!(client.is_alive());
+        client_transport.close().await?;
+        start.await.unwrap()?;
+        assert!(!client.is_alive());
+        Ok(())
+    }
 }
@@ -0,0 +1,175 @@
+//! Per request context handed to handlers and tools, and the context of notification handlers
+//! gives access to the request metadata, cancellation and the peer on the other side
+use crate::error::Result;
+use crate::{
+    elicitation,
+    logging::Logger,
+    protocol::{Peer, RequestOptions},
+    roots, sampling,
+    transport::RequestId,
+    types::{
+        CreateMessageRequest, CreateMessageResult, ElicitRequest, ElicitResult, LoggingLevel,
+        ProgressNotification, ProgressToken, Root,
+    },
+};
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Clone)]
+pub struct RequestContext {
+    id: RequestId,
+    meta: Option<serde_json::Value>,
+    cancellation: CancellationToken,
+    peer: Arc<dyn Peer>,
+}
+
+impl RequestContext {
+    pub fn new(
+        id: RequestId,
+        meta: Option<serde_json::Value>,
+        cancellation: CancellationToken,
+        peer: Arc<dyn Peer>,
+    ) -> Self {
+        Self {
+            id,
+            meta,
+            cancellation,
+            peer,
+        }
+    }
+
+    /// The id of the request being handled
+    pub fn id(&self) -> &RequestId {
+        &self.id
+    }
+
+    /// The `_meta` field of the request params, if any
+    pub fn meta(&self) -> Option<&serde_json::Value> {
+        self.meta.as_ref()
+    }
+
+    /// Token cancelled once the request is cancelled by the peer
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
+    /// The peer the request came from, used to send notifications or requests back
+    pub fn peer(&self) -> &Arc<dyn Peer> {
+        &self.peer
+    }
+
+    /// Reporter for progress of this request
+    /// reports are dropped if the peer did not ask for progress
+    pub fn progress(&self) -> ProgressReporter {
+        let token = self
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.get("progressToken"))
+            .and_then(|token| serde_json::from_value(token.clone()).ok());
+        ProgressReporter {
+            token,
+            peer: self.peer.clone(),
+        }
+    }
+
+    /// Ask the client to sample an LLM, see [`crate::sampling`]
+    /// fails if the client did not advertise the sampling capability
+    pub async fn create_message(
+        &self,
+        request: CreateMessageRequest,
+        options: RequestOptions,
+    ) -> Result<CreateMessageResult> {
+        sampling::create_message(self.peer.as_ref(), request, options).await
+    }
+
+    /// Ask the user for structured input through the client, see [`crate::elicitation`]
+    /// fails if the client did not advertise the elicitation capability
+    pub async fn elicit(
+        &self,
+        request: ElicitRequest,
+        options: RequestOptions,
+    ) -> Result<ElicitResult> {
+        elicitation::elicit(self.peer.as_ref(), request, options).await
+    }
+
+    /// Ask the client for its roots, see [`crate::roots`]
+    /// fails if the client did not advertise the roots capability
+    pub async fn list_roots(&self) -> Result<Vec<Root>> {
+        roots::list_roots(self.peer.as_ref()).await
+    }
+
+    /// Logger sending `notifications/message` to the peer
+    pub fn logger(&self) -> Logger {
+        Logger::new(self.peer.clone())
+    }
+
+    /// Send a log message to the peer, dropped if below the level the peer asked for
+    pub async fn log(&self, level: LoggingLevel, data: serde_json::Value) -> Result<()> {
+        self.logger().log(level, data).await
+    }
+}
+
+/// Context handed to notification handlers, gives access to the peer the notification came from
+/// e.g. to fetch the new roots after `notifications/roots/list_changed`
+#[derive(Clone)]
+pub struct NotificationContext {
+    peer: Arc<dyn Peer>,
+}
+
+impl NotificationContext {
+    pub fn new(peer: Arc<dyn Peer>) -> Self {
+        Self { peer }
+    }
+
+    /// The peer the notification came from, used to send notifications or requests back
+    pub fn peer(&self) -> &Arc<dyn Peer> {
+        &self.peer
+    }
+
+    /// Ask the client for its roots, see [`crate::roots`]
+    /// fails if the client did not advertise the roots capability
+    pub async fn list_roots(&self) -> Result<Vec<Root>> {
+        roots::list_roots(self.peer.as_ref()).await
+    }
+
+    /// Logger sending `notifications/message` to the peer
+    pub fn logger(&self) -> Logger {
+        Logger::new(self.peer.clone())
+    }
+}
+
+/// Sends `notifications/progress` tied to the originating request
+#[derive(Clone)]
+pub struct ProgressReporter {
+    token: Option<ProgressToken>,
+    peer: Arc<dyn Peer>,
+}
+
+impl ProgressReporter {
+    /// The progress token sent by the peer, `None` if it did not ask for progress
+    pub fn token(&self) -> Option<&ProgressToken> {
+        self.token.as_ref()
+    }
+
+    /// Report progress, `progress` should increase with each call
+    pub async fn report(&self, progress: f64, total: Option<f64>) -> Result<()> {
+        let Some(token) = self.token.clone() else {
+            return Ok(());
+        };
+        let notification = ProgressNotification {
+            progress_token: token,
+            progress,
+            total,
+        };
+        self.peer
+            .notify(
+                "notifications/progress",
+                Some(serde_json::to_value(notification)?),
+            )
+            .await
+    }
+}
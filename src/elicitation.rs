@@ -0,0 +1,158 @@
+//! Elicitation lets a server ask the user for structured input with `elicitation/create`
+//! e.g. to confirm parameters in the middle of a tool call
+use crate::context::RequestContext;
+use crate::error::{McpError, Result};
+use crate::protocol::{Peer, RequestOptions, check_client_capability};
+use crate::types::{ElicitAction, ElicitRequest, ElicitResult, ElicitationSchema, PrimitiveSchema};
+use async_trait::async_trait;
+
+/// Answers `elicitation/create` requests on the client, usually by showing a form to the user
+#[async_trait]
+pub trait ElicitationHandler: Send + Sync + 'static {
+    async fn elicit(&self, request: ElicitRequest, ctx: RequestContext) -> Result<ElicitResult>;
+}
+
+/// Send `elicitation/create` to `peer` and wait for the user to answer
+/// fails if the client did not advertise the elicitation capability,
+/// accepted content is checked against the requested schema
+pub(crate) async fn elicit(
+    peer: &dyn Peer,
+    request: ElicitRequest,
+    options: RequestOptions,
+) -> Result<ElicitResult> {
+    check_client_capability(peer, "elicitation", |capabilities| {
+        capabilities.elicitation.is_some()
+    })?;
+    let schema = request.requested_schema.clone();
+    let result = peer
+        .request(
+            "elicitation/create",
+            Some(serde_json::to_value(request)?),
+            options,
+        )
+        .await?
+        .into_result()?;
+    let result: ElicitResult = serde_json::from_value(result)
+        .map_err(|e| McpError::Protocol(format!("Invalid elicitation/create response: {e}")))?;
+    if result.action == ElicitAction::Accept {
+        validate(&schema, result.content.as_ref())?;
+    }
+    Ok(result)
+}
+
+fn validate(
+    schema: &ElicitationSchema,
+    content: Option<&serde_json::Map<String, serde_json::Value>>,
+) -> Result<()> {
+    let invalid =
+        |message: String| McpError::Protocol(format!("Invalid elicitation content: {message}"));
+    let empty = serde_json::Map::new();
+    let content = content.unwrap_or(&empty);
+    for name in schema.required.iter().flatten() {
+        if !content.contains_key(name) {
+            return Err(invalid(format!("missing required `{name}`")));
+        }
+    }
+    for (name, value) in content {
+        let Some(property) = schema.properties.get(name) else {
+            return Err(invalid(format!("unexpected `{name}`")));
+        };
+        let in_range = |minimum: &Option<f64>, maximum: &Option<f64>| {
+            value.as_f64().is_some_and(|value| {
+                minimum.is_none_or(|min| value >= min) && maximum.is_none_or(|max| value <= max)
+            })
+        };
+        let valid = match property {
+            PrimitiveSchema::String {
+                min_length,
+                max_length,
+                enum_values,
+                ..
+            } => value.as_str().is_some_and(|value| {
+                let length = value.chars().count() as u64;
+                min_length.is_none_or(|min| length >= min)
+                    && max_length.is_none_or(|max| length <= max)
+                    && enum_values
+                        .as_ref()
+                        .is_none_or(|values| values.iter().any(|v| v == value))
+            }),
+            PrimitiveSchema::Number {
+                minimum, maximum, ..
+            } => in_range(minimum, maximum),
+            PrimitiveSchema::Integer {
+                minimum, maximum, ..
+            } => (value.is_i64() || value.is_u64()) && in_range(minimum, maximum),
+            PrimitiveSchema::Boolean { .. } => value.is_boolean(),
+        };
+        if !valid {
+            return Err(invalid(format!("`{name}` does not match its schema")));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate() {
+        let schema = ElicitationSchema::new()
+            .required_property(
+                "env",
+                PrimitiveSchema::enumeration("Target", vec!["staging".into(), "prod".into()]),
+            )
+            .property("replicas", PrimitiveSchema::integer("Replica count"));
+        let check = |value: serde_json::Value| validate(&schema, value.as_object()).is_ok();
+
+        assert!(check(json!({"env": "prod", "replicas": 3})));
+        assert!(!check(json!({"replicas": 3})));
+        assert!(!check(json!({"env": "dev"})));
+        assert!(!check(json!({"env": "prod", "replicas": 1.5})));
+        assert!(!check(json!({"env": "prod", "other": 1})));
+    }
+
+    #[test]
+    fn test_validate_constraints() {
+        let schema = ElicitationSchema::new()
+            .property(
+                "name",
+                PrimitiveSchema::String {
+                    title: None,
+                    description: None,
+                    min_length: Some(2),
+                    max_length: Some(4),
+                    format: None,
+                    enum_values: None,
+                    enum_names: None,
+                },
+            )
+            .property(
+                "replicas",
+                PrimitiveSchema::Integer {
+                    title: None,
+                    description: None,
+                    minimum: Some(1.0),
+                    maximum: Some(10.0),
+                },
+            )
+            .property(
+                "ratio",
+                PrimitiveSchema::Number {
+                    title: None,
+                    description: None,
+                    minimum: Some(0.0),
+                    maximum: Some(1.0),
+                },
+            );
+        let check = |value: serde_json::Value| validate(&schema, value.as_object()).is_ok();
+
+        assert!(check(json!({"name": "éé", "replicas": 10, "ratio": 0.5})));
+        assert!(!check(json!({"name": "a"})));
+        assert!(!check(json!({"name": "abcde"})));
+        assert!(!check(json!({"replicas": 0})));
+        assert!(!check(json!({"replicas": 11})));
+        assert!(!check(json!({"ratio": 1.5})));
+    }
+}
@@ -0,0 +1,207 @@
+//! Typed errors returned by the protocol, client and server
+//! handlers return [`McpError`] to choose the JSON-RPC error sent to the peer
+use crate::transport::JsonRpcError;
+use crate::types::ErrorCode;
+use std::fmt;
+
+pub type Result<T, E = McpError> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum McpError {
+    /// The transport failed to send or receive a message
+    Transport(anyhow::Error),
+    /// The peer sent something that does not follow the protocol
+    Protocol(String),
+    /// A JSON-RPC error, either returned by the peer or to be sent to it
+    Rpc(JsonRpcError),
+    /// No response was received before the request timeout
+    Timeout,
+    /// The request was cancelled through [`RequestOptions::cancellation`](crate::protocol::RequestOptions::cancellation)
+    /// before a response was received
+    Cancelled,
+    /// The connection was closed before a response was received
+    ConnectionClosed,
+}
+
+impl McpError {
+    /// A JSON-RPC error with the given code
+    pub fn rpc(code: impl Into<i32>, message: impl Into<String>) -> Self {
+        McpError::Rpc(JsonRpcError {
+            code: code.into(),
+            message: message.into(),
+            data: None,
+        })
+    }
+
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self::rpc(ErrorCode::InvalidParams, message)
+    }
+
+    pub fn method_not_found(method: &str) -> Self {
+        Self::rpc(
+            ErrorCode::MethodNotFound,
+            format!("Method not found: {method}"),
+        )
+    }
+
+    pub fn resource_not_found(uri: &str) -> Self {
+        Self::rpc(
+            ErrorCode::ResourceNotFound,
+            format!("Resource not found: {uri}"),
+        )
+        .with_data(serde_json::json!({ "uri": uri }))
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::rpc(ErrorCode::InternalError, message)
+    }
+
+    /// Attach data to a JSON-RPC error, other errors are returned unchanged
+    pub fn with_data(self, data: serde_json::Value) -> Self {
+        match self {
+            McpError::Rpc(error) => McpError::Rpc(JsonRpcError {
+                data: Some(data),
+                ..error
+            }),
+            other => other,
+        }
+    }
+
+    /// The JSON-RPC error code of this error
+    pub fn code(&self) -> i32 {
+        match self {
+            McpError::Rpc(error) => error.code,
+            McpError::Timeout => ErrorCode::RequestTimeout as i32,
+            McpError::ConnectionClosed => ErrorCode::ConnectionClosed as i32,
+            McpError::Transport(_) | McpError::Protocol(_) | McpError::Cancelled => {
+                ErrorCode::InternalError as i32
+            }
+        }
+    }
+
+    /// The JSON-RPC error sent to the peer when a handler fails with this error
+    pub fn into_json_rpc_error(self) -> JsonRpcError {
+        match self {
+            McpError::Rpc(error) => error,
+            other => JsonRpcError {
+                code: other.code(),
+                message: other.to_string(),
+                data: None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for McpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            McpError::Transport(e) => write!(f, "Transport error: {e}"),
+            McpError::Protocol(message) => write!(f, "Protocol error: {message}"),
+            McpError::Rpc(error) => write!(f, "{}: {}", error.code, error.message),
+            McpError::Timeout => write!(f, "Request timed out"),
+            McpError::Cancelled => write!(f, "Request cancelled"),
+            McpError::ConnectionClosed => write!(f, "Connection closed"),
+        }
+    }
+}
+
+impl std::error::Error for McpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            McpError::Transport(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<JsonRpcError> for McpError {
+    fn from(error: JsonRpcError) -> Self {
+        McpError::Rpc(error)
+    }
+}
+
+/// Errors raised inside handlers with `?` become internal errors
+/// unless they wrap an [`McpError`]
+impl From<anyhow::Error> for McpError {
+    fn from(e: anyhow::Error) -> Self {
+        match e.downcast::<McpError>() {
+            Ok(e) => e,
+            Err(e) => McpError::internal(e.to_string()),
+        }
+    }
+}
+
+impl From<serde_json::Error> for McpError {
+    fn from(e: serde_json::Error) -> Self {
+        McpError::internal(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for McpError {
+    fn from(e: std::io::Error) -> Self {
+        McpError::internal(e.to_string())
+    }
+}
+
+impl From<ErrorCode> for i32 {
+    fn from(code: ErrorCode) -> Self {
+        code as i32
+    }
+}
+
+impl TryFrom<i32> for ErrorCode {
+    type Error = i32;
+
+    fn try_from(code: i32) -> Result<Self, i32> {
+        match code {
+            -1 => Ok(ErrorCode::ConnectionClosed),
+            -2 => Ok(ErrorCode::RequestTimeout),
+            -32700 => Ok(ErrorCode::ParseError),
+            -32600 => Ok(ErrorCode::InvalidRequest),
+            -32601 => Ok(ErrorCode::MethodNotFound),
+            -32602 => Ok(ErrorCode::InvalidParams),
+            -32603 => Ok(ErrorCode::InternalError),
+            -32002 => Ok(ErrorCode::ResourceNotFound),
+            code => Err(code),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_anyhow_round_trip_keeps_mcp_error() {
+        let error: anyhow::Error = McpError::invalid_params("bad").into();
+        let error = McpError::from(error);
+        assert_eq!(error.code(), ErrorCode::InvalidParams as i32);
+
+        let error = McpError::from(anyhow::anyhow!("boom"));
+        assert_eq!(error.code(), ErrorCode::InternalError as i32);
+    }
+
+    #[test]
+    fn test_into_json_rpc_error() {
+        let error = McpError::rpc(-32000, "app error")
+            .with_data(serde_json::json!({"retry": true}))
+            .into_json_rpc_error();
+        assert_eq!(error.code, -32000);
+        assert_eq!(error.data, Some(serde_json::json!({"retry": true})));
+        assert_eq!(
+            McpError::Timeout.into_json_rpc_error().code,
+            ErrorCode::RequestTimeout as i32
+        );
+    }
+
+    #[test]
+    fn test_transport_error_source() {
+        use std::error::Error;
+        let io = std::io::Error::new(std::io::ErrorKind::BrokenPipe, "pipe closed");
+        let error = McpError::Transport(io.into());
+        let source = error.source().unwrap();
+        assert_eq!(source.to_string(), "pipe closed");
+        assert!(source.downcast_ref::<std::io::Error>().is_some());
+        assert!(McpError::Timeout.source().is_none());
+    }
+}
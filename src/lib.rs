@@ -1,5 +1,13 @@
 pub mod client;
+pub mod context;
+pub mod elicitation;
+pub mod error;
+pub mod logging;
+pub mod prompts;
 pub mod protocol;
+pub mod resources;
+pub mod roots;
+pub mod sampling;
 pub mod server;
 pub mod tools;
 pub mod transport;
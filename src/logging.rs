@@ -0,0 +1,288 @@
+//! Logging to the client with `notifications/message`
+//! the client picks the minimum level with `logging/setLevel`
+use crate::error::Result;
+use crate::protocol::Peer;
+use crate::types::{LoggingLevel, LoggingMessageNotification};
+use std::cell::Cell;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::Poll;
+use tokio::sync::mpsc;
+use tracing::field::{Field, Visit};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// The minimum level of messages sent to the peer, shared by a connection
+/// every message is sent until the peer sets a level
+#[derive(Clone, Default)]
+pub struct LogLevelFilter(Arc<RwLock<Option<LoggingLevel>>>);
+
+impl LogLevelFilter {
+    pub fn get(&self) -> Option<LoggingLevel> {
+        *self.0.read().unwrap()
+    }
+
+    pub fn set(&self, level: LoggingLevel) {
+        *self.0.write().unwrap() = Some(level);
+    }
+
+    pub fn enabled(&self, level: LoggingLevel) -> bool {
+        self.get().is_none_or(|min| level >= min)
+    }
+
+    /// Like [`enabled`](Self::enabled) with `default` as the minimum until the peer sets a level
+    pub fn enabled_or(&self, level: LoggingLevel, default: LoggingLevel) -> bool {
+        level >= self.get().unwrap_or(default)
+    }
+}
+
+/// Sends log messages to the peer, dropping those below its log level
+#[derive(Clone)]
+pub struct Logger {
+    peer: Arc<dyn Peer>,
+    name: Option<String>,
+}
+
+impl Logger {
+    pub fn new(peer: Arc<dyn Peer>) -> Self {
+        Self { peer, name: None }
+    }
+
+    /// Set the `logger` name sent with each message
+    pub fn named(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn enabled(&self, level: LoggingLevel) -> bool {
+        self.peer.log_level().enabled(level)
+    }
+
+    pub async fn log(&self, level: LoggingLevel, data: serde_json::Value) -> Result<()> {
+        if !self.enabled(level) {
+            return Ok(());
+        }
+        let message = LoggingMessageNotification {
+            level,
+            logger: self.name.clone(),
+            data,
+        };
+        self.peer
+            .notify(
+                "notifications/message",
+                Some(serde_json::to_value(message)?),
+            )
+            .await
+    }
+}
+
+/// Targets of the crates sending the messages, e.g. from connection tasks,
+/// their events are not forwarded as that would log again
+const SKIPPED_TARGETS: &[&str] = &[
+    env!("CARGO_CRATE_NAME"),
+    "hyper",
+    "h2",
+    "reqwest",
+    "axum",
+    "tower",
+    "tungstenite",
+    "tokio_tungstenite",
+];
+
+thread_local! {
+    /// set while the layer is sending a message on this thread
+    static SENDING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Sets [`SENDING`] during each poll of the wrapped future,
+/// events raised while sending a message are then not forwarded whatever their target
+struct Sending<F>(Pin<Box<F>>);
+
+impl<F: Future> Future for Sending<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<F::Output> {
+        let previous = SENDING.replace(true);
+        let result = self.0.as_mut().poll(cx);
+        SENDING.set(previous);
+        result
+    }
+}
+
+/// A `tracing` layer forwarding events to the peer as `notifications/message`
+/// events raised while sending a message and events of the transport crates are skipped,
+/// sending them would log again
+///
+/// Until the peer sets a level only events at [`LoggingLevel::Info`] and above are forwarded,
+/// see [`McpLoggingLayer::default_level`]
+pub struct McpLoggingLayer {
+    sender: mpsc::UnboundedSender<(LoggingLevel, Option<String>, serde_json::Value)>,
+    peer: Arc<dyn Peer>,
+    default_level: LoggingLevel,
+}
+
+impl McpLoggingLayer {
+    /// Must be called from within a tokio runtime, messages are sent from a spawned task
+    pub fn new(logger: Logger) -> Self {
+        let (sender, mut receiver) =
+            mpsc::unbounded_channel::<(LoggingLevel, Option<String>, serde_json::Value)>();
+        let peer = logger.peer.clone();
+        tokio::spawn(async move {
+            while let Some((level, name, data)) = receiver.recv().await {
+                let logger = match name {
+                    Some(name) => logger.clone().named(name),
+                    None => logger.clone(),
+                };
+                // the peer is gone, there is no one left to log to
+                if Sending(Box::pin(logger.log(level, data))).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            sender,
+            peer,
+            default_level: LoggingLevel::Info,
+        }
+    }
+
+    /// The minimum level of forwarded events until the peer sets one, `Info` by default
+    pub fn default_level(mut self, level: LoggingLevel) -> Self {
+        self.default_level = level;
+        self
+    }
+}
+
+impl<S: tracing::Subscriber> Layer<S> for McpLoggingLayer {
+    fn on_event(&self, event: &tracing::Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if SENDING.get()
+            || SKIPPED_TARGETS
+                .iter()
+                .any(|target| metadata.target().starts_with(target))
+        {
+            return;
+        }
+        let level = logging_level(metadata.level());
+        if !self.peer.log_level().enabled_or(level, self.default_level) {
+            return;
+        }
+        let mut visitor = JsonVisitor(serde_json::Map::new());
+        event.record(&mut visitor);
+        let _ = self.sender.send((
+            level,
+            Some(metadata.target().to_string()),
+            serde_json::Value::Object(visitor.0),
+        ));
+    }
+}
+
+fn logging_level(level: &tracing::Level) -> LoggingLevel {
+    match *level {
+        tracing::Level::ERROR => LoggingLevel::Error,
+        tracing::Level::WARN => LoggingLevel::Warning,
+        tracing::Level::INFO => LoggingLevel::Info,
+        tracing::Level::DEBUG | tracing::Level::TRACE => LoggingLevel::Debug,
+    }
+}
+
+/// Collects the fields of an event into a json object
+struct JsonVisitor(serde_json::Map<String, serde_json::Value>);
+
+impl Visit for JsonVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().to_string(), value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{value:?}").into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::McpError;
+    use crate::protocol::RequestOptions;
+    use crate::transport::JsonRpcResponse;
+    use async_trait::async_trait;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Peer whose sending logs an event, like the HTTP client of a transport
+    #[derive(Default)]
+    struct NoisyPeer {
+        sent: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Peer for NoisyPeer {
+        async fn notify(&self, _method: &str, _params: Option<serde_json::Value>) -> Result<()> {
+            tracing::info!(target: "some_http_client", "sending request");
+            self.sent.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        async fn request(
+            &self,
+            _method: &str,
+            _params: Option<serde_json::Value>,
+            _options: RequestOptions,
+        ) -> Result<JsonRpcResponse> {
+            Err(McpError::internal("NoisyPeer does not answer requests"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_events_raised_while_sending_are_skipped() {
+        let peer = Arc::new(NoisyPeer::default());
+        let layer = McpLoggingLayer::new(Logger::new(peer.clone()));
+        let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(layer));
+
+        tracing::info!(target: "app", "hello");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(peer.sent.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_debug_events_skipped_until_level_set() {
+        let peer = Arc::new(NoisyPeer::default());
+        let layer = McpLoggingLayer::new(Logger::new(peer.clone()));
+        let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(layer));
+
+        tracing::debug!(target: "app", "internals");
+        tracing::trace!(target: "app", "more internals");
+        tracing::warn!(target: "app", "careful");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(peer.sent.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_log_level_filter() {
+        let filter = LogLevelFilter::default();
+        assert!(filter.enabled(LoggingLevel::Debug));
+        assert!(!filter.enabled_or(LoggingLevel::Debug, LoggingLevel::Info));
+        filter.set(LoggingLevel::Warning);
+        assert!(!filter.enabled(LoggingLevel::Info));
+        assert!(!filter.enabled_or(LoggingLevel::Info, LoggingLevel::Debug));
+        assert!(filter.enabled(LoggingLevel::Warning));
+        assert!(filter.enabled(LoggingLevel::Emergency));
+    }
+}
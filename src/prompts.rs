@@ -0,0 +1,152 @@
+use crate::context::RequestContext;
+use crate::error::{McpError, Result};
+use crate::resources::paginate;
+use crate::types::{
+    Completion, CompletionArgument, GetPromptRequest, GetPromptResult, ListRequest, Prompt,
+    PromptArgument, PromptsListResponse,
+};
+use async_trait::async_trait;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+#[async_trait]
+pub trait PromptProvider: Send + Sync + 'static {
+    fn name(&self) -> String;
+    fn description(&self) -> Option<String> {
+        None
+    }
+    fn arguments(&self) -> Vec<PromptArgument> {
+        Vec::new()
+    }
+    /// Render the prompt, required arguments are checked before this is called
+    async fn get(
+        &self,
+        arguments: HashMap<String, String>,
+        ctx: RequestContext,
+    ) -> Result<GetPromptResult>;
+    /// Suggest values for `argument` starting from the partial `value`
+    /// returns no suggestions by default
+    async fn complete(
+        &self,
+        _argument: &str,
+        _value: &str,
+        _ctx: RequestContext,
+    ) -> Result<Completion> {
+        Ok(Completion::default())
+    }
+    fn as_definition(&self) -> Prompt {
+        let arguments = self.arguments();
+        Prompt {
+            name: self.name(),
+            description: self.description(),
+            arguments: (!arguments.is_empty()).then_some(arguments),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Prompts {
+    // ordered by name so that cursors stay valid between pages
+    prompts: BTreeMap<String, Arc<dyn PromptProvider>>,
+    page_size: Option<usize>,
+}
+
+impl Prompts {
+    pub fn add_prompt(&mut self, prompt: impl PromptProvider) {
+        self.prompts.insert(prompt.name(), Arc::new(prompt));
+    }
+
+    /// Split `prompts/list` responses into pages of at most `page_size` prompts
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = Some(page_size.max(1));
+        self
+    }
+
+    pub fn list_prompts(&self, request: ListRequest) -> Result<PromptsListResponse> {
+        let (prompts, next_cursor) = paginate(
+            self.prompts.values().map(|prompt| prompt.as_definition()),
+            request.cursor.as_deref(),
+            self.page_size,
+        )?;
+        Ok(PromptsListResponse {
+            prompts,
+            next_cursor,
+            meta: None,
+        })
+    }
+
+    pub async fn get_prompt(
+        &self,
+        request: GetPromptRequest,
+        ctx: RequestContext,
+    ) -> Result<GetPromptResult> {
+        let prompt = self.get(&request.name)?;
+        let arguments = request.arguments.unwrap_or_default();
+        check_required_arguments(&prompt.arguments(), &arguments)?;
+        prompt.get(arguments, ctx).await
+    }
+
+    /// Complete an argument of the prompt called `name`
+    pub async fn complete(
+        &self,
+        name: &str,
+        argument: CompletionArgument,
+        ctx: RequestContext,
+    ) -> Result<Completion> {
+        self.get(name)?
+            .complete(&argument.name, &argument.value, ctx)
+            .await
+    }
+
+    fn get(&self, name: &str) -> Result<&Arc<dyn PromptProvider>> {
+        self.prompts
+            .get(name)
+            .ok_or_else(|| McpError::invalid_params(format!("Unknown prompt: {name}")))
+    }
+}
+
+fn check_required_arguments(
+    expected: &[PromptArgument],
+    arguments: &HashMap<String, String>,
+) -> Result<()> {
+    let missing: Vec<_> = expected
+        .iter()
+        .filter(|argument| argument.required == Some(true))
+        .filter(|argument| !arguments.contains_key(&argument.name))
+        .map(|argument| argument.name.as_str())
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(McpError::invalid_params(format!(
+        "Missing required arguments: {}",
+        missing.join(", ")
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_required_arguments() {
+        let expected = vec![
+            PromptArgument {
+                name: "code".to_string(),
+                description: None,
+                required: Some(true),
+            },
+            PromptArgument {
+                name: "style".to_string(),
+                description: None,
+                required: None,
+            },
+        ];
+        let mut arguments = HashMap::new();
+        assert!(check_required_arguments(&expected, &arguments).is_err());
+        arguments.insert("code".to_string(), "fn main() {}".to_string());
+        assert!(check_required_arguments(&expected, &arguments).is_ok());
+    }
+}
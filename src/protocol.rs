@@ -1,10 +1,13 @@
+use super::context::{NotificationContext, RequestContext};
+use super::error::{McpError, Result};
+use super::logging::LogLevelFilter;
 use super::transport::{
-    JsonRpcError, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, Message,
-    Transport,
+    InvalidMessage, JsonRpcError, JsonRpcMessage, JsonRpcNotification, JsonRpcRequest,
+    JsonRpcResponse, RequestId, Transport, parse_batch_element,
+};
+use super::types::{
+    CancelledNotification, ClientCapabilities, ErrorCode, ProgressNotification, ProgressToken,
 };
-use super::types::ErrorCode;
-use anyhow::Result;
-use anyhow::anyhow;
 use async_trait::async_trait;
 use serde::Serialize;
 use serde::de::DeserializeOwned;
@@ -15,18 +18,61 @@ use std::{
     sync::{Arc, atomic::AtomicU64},
 };
 use tokio::sync::Mutex;
+use tokio::sync::Notify;
+use tokio::sync::Semaphore;
 use tokio::sync::oneshot;
-use tokio::time::timeout;
-use tracing::debug;
+use tokio::task::AbortHandle;
+use tokio::time::{Instant, sleep, sleep_until};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
 
-#[derive(Clone)]
 pub struct Protocol<T: Transport> {
     transport: Arc<T>,
 
     request_id: Arc<AtomicU64>,
-    pending_requests: Arc<Mutex<HashMap<u64, oneshot::Sender<JsonRpcResponse>>>>,
-    request_handlers: Arc<Mutex<HashMap<String, Box<dyn RequestHandler>>>>,
-    notification_handlers: Arc<Mutex<HashMap<String, Box<dyn NotificationHandler>>>>,
+    pending_requests: Arc<Mutex<HashMap<RequestId, oneshot::Sender<JsonRpcResponse>>>>,
+    request_handlers: Arc<HashMap<String, Arc<dyn RequestHandler>>>,
+    notification_handlers: Arc<HashMap<String, Box<dyn NotificationHandler>>>,
+    /// limits the number of request and notification handlers running at the same time
+    request_permits: Arc<Semaphore>,
+    /// requests from the peer currently being handled, used for cancellation
+    in_flight: Arc<std::sync::Mutex<HashMap<RequestId, InFlightRequest>>>,
+    /// progress callbacks of our pending requests
+    progress_callbacks: Arc<std::sync::Mutex<HashMap<ProgressToken, ProgressHandler>>>,
+    /// minimum level of log messages sent to the peer
+    log_level: LogLevelFilter,
+    /// capabilities the client sent in `initialize`
+    client_capabilities: SharedClientCapabilities,
+}
+
+struct InFlightRequest {
+    cancellation: CancellationToken,
+    abort: AbortHandle,
+}
+
+/// Progress callback of a pending request, `reset` restarts the request timeout
+#[derive(Clone)]
+struct ProgressHandler {
+    callback: ProgressCallback,
+    reset: Arc<Notify>,
+}
+
+// manual impl, derive would require `T: Clone`
+impl<T: Transport> Clone for Protocol<T> {
+    fn clone(&self) -> Self {
+        Self {
+            transport: self.transport.clone(),
+            request_id: self.request_id.clone(),
+            pending_requests: self.pending_requests.clone(),
+            request_handlers: self.request_handlers.clone(),
+            notification_handlers: self.notification_handlers.clone(),
+            request_permits: self.request_permits.clone(),
+            in_flight: self.in_flight.clone(),
+            progress_callbacks: self.progress_callbacks.clone(),
+            log_level: self.log_level.clone(),
+            client_capabilities: self.client_capabilities.clone(),
+        }
+    }
 }
 
 impl<T: Transport> Protocol<T> {
@@ -34,15 +80,24 @@ impl<T: Transport> Protocol<T> {
         ProtocolBuilder::new(transport)
     }
 
-    pub fn notify(&self, method: &str, params: Option<serde_json::Value>) -> Result<()> {
+    /// The minimum level of log messages the peer wants to receive
+    pub fn log_level(&self) -> &LogLevelFilter {
+        &self.log_level
+    }
+
+    /// The capabilities the client sent in `initialize`, only set on servers
+    pub fn client_capabilities(&self) -> &SharedClientCapabilities {
+        &self.client_capabilities
+    }
+
+    pub async fn notify(&self, method: &str, params: Option<serde_json::Value>) -> Result<()> {
         let notification = JsonRpcNotification {
             method: method.to_string(),
             params,
             ..Default::default()
         };
         let msg = JsonRpcMessage::Notification(notification);
-        self.transport.send(&msg)?;
-        Ok(())
+        self.transport.send(&msg).await.map_err(McpError::Transport)
     }
 
     pub async fn request(
@@ -51,7 +106,21 @@ impl<T: Transport> Protocol<T> {
         params: Option<serde_json::Value>,
         options: RequestOptions,
     ) -> Result<JsonRpcResponse> {
-        let id = self.request_id.fetch_add(1, Ordering::SeqCst);
+        let id = RequestId::Number(self.request_id.fetch_add(1, Ordering::SeqCst) as i64);
+        let mut params = params;
+        let progress = Arc::new(Notify::new());
+        if let Some(callback) = options.progress.clone() {
+            let token = progress_token(&id);
+            params = Some(with_progress_token(params, &token)?);
+            let handler = ProgressHandler {
+                callback,
+                reset: progress.clone(),
+            };
+            self.progress_callbacks
+                .lock()
+                .unwrap()
+                .insert(token, handler);
+        }
 
         // Create a oneshot channel for this request
         let (tx, rx) = oneshot::channel();
@@ -59,109 +128,585 @@ impl<T: Transport> Protocol<T> {
         // Store the sender
         {
             let mut pending = self.pending_requests.lock().await;
-            pending.insert(id, tx);
+            pending.insert(id.clone(), tx);
         }
 
         // Send the request
         let msg = JsonRpcMessage::Request(JsonRpcRequest {
-            id,
+            id: id.clone(),
             method: method.to_string(),
             params,
             ..Default::default()
         });
-        self.transport.send(&msg)?;
+        // Cancels the request on the peer if this future is dropped before completion
+        let guard = CancelOnDrop {
+            protocol: Some(self.clone()),
+            id: id.clone(),
+        };
+        if let Err(e) = self.transport.send(&msg).await {
+            guard.disarm();
+            self.pending_requests.lock().await.remove(&id);
+            self.remove_progress_callback(&id);
+            return Err(McpError::Transport(e));
+        }
 
-        // Wait for response with timeout
-        match timeout(options.timeout, rx)
-            .await
-            .map_err(|_| anyhow!("Request timed out"))?
-        {
-            Ok(response) => Ok(response),
-            Err(_) => {
-                // Clean up the pending request if receiver was dropped
-                let mut pending = self.pending_requests.lock().await;
-                pending.remove(&id);
-                Err(anyhow!("Request cancelled"))
+        // Wait for response with timeout, progress from the peer restarts the timeout
+        let deadline = options.max_total_timeout.map(|max| Instant::now() + max);
+        let total_timeout = async {
+            match deadline {
+                Some(deadline) => sleep_until(deadline).await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::pin!(rx, total_timeout);
+        let result = loop {
+            tokio::select! {
+                response = &mut rx => break Ok(response),
+                _ = progress.notified() => {}
+                _ = sleep(options.timeout) => break Err(McpError::Timeout),
+                _ = &mut total_timeout => break Err(McpError::Timeout),
+                _ = options.cancellation.cancelled() => break Err(McpError::Cancelled),
+            }
+        };
+        guard.disarm();
+        self.remove_progress_callback(&id);
+        match result {
+            Ok(Ok(response)) => Ok(response),
+            // the sender is dropped once the connection closes
+            Ok(Err(_)) => Err(McpError::ConnectionClosed),
+            Err(e) => {
+                let reason = match e {
+                    McpError::Cancelled => "Request cancelled",
+                    _ => "Request timed out",
+                };
+                self.cancel_request(&id, reason).await;
+                Err(e)
             }
         }
     }
 
+    fn remove_progress_callback(&self, id: &RequestId) {
+        self.progress_callbacks
+            .lock()
+            .unwrap()
+            .remove(&progress_token(id));
+    }
+
+    /// Forget a pending outgoing request and tell the peer to stop working on it
+    async fn cancel_request(&self, id: &RequestId, reason: &str) {
+        self.pending_requests.lock().await.remove(id);
+        self.remove_progress_callback(id);
+        let notification = CancelledNotification {
+            request_id: id.clone(),
+            reason: Some(reason.to_string()),
+        };
+        let params = serde_json::to_value(notification).ok();
+        if let Err(e) = self.notify("notifications/cancelled", params).await {
+            warn!("Failed to send cancellation for request {id}: {e}");
+        }
+    }
+
     pub async fn listen(&self) -> Result<()> {
         debug!("Listening for requests");
+        let result = self.receive_messages().await;
+        // fail the requests still waiting for a response
+        self.pending_requests.lock().await.clear();
+        // stop the handlers, their responses could not be sent anymore
+        for (_, request) in self.in_flight.lock().unwrap().drain() {
+            request.cancellation.cancel();
+            request.abort.abort();
+        }
+        result
+    }
+
+    async fn receive_messages(&self) -> Result<()> {
         loop {
-            let message: Message = self.transport.receive()?;
+            let message = match self.transport.receive().await {
+                Ok(Some(message)) => message,
+                Ok(None) => break,
+                Err(e) => {
+                    // answer malformed input and keep going, other errors end the connection
+                    let Some(response) = malformed_message_response(&e) else {
+                        // keep the code of typed errors, e.g. a closed connection
+                        return Err(e.downcast::<McpError>().unwrap_or_else(McpError::Transport));
+                    };
+                    warn!("Received malformed message: {e}");
+                    self.transport
+                        .send(&JsonRpcMessage::Response(response))
+                        .await
+                        .map_err(McpError::Transport)?;
+                    continue;
+                }
+            };
             match message {
-                JsonRpcMessage::Request(request) => self.handle_request(request).await?,
-                JsonRpcMessage::Response(response) => {
-                    // Remove and send response through the channel
-                    let id = response.id;
-                    let mut pending = self.pending_requests.lock().await;
-                    // Store the result of remove in a local variable first to control drop order
-                    let tx_opt = pending.remove(&id);
-                    if let Some(tx) = tx_opt {
-                        // If send fails, the receiver was dropped, just continue
-                        let _ = tx.send(response);
+                JsonRpcMessage::Batch(messages) => self.handle_batch(messages).await?,
+                message => {
+                    if let Some(response) = self.handle_message(message).await? {
+                        self.spawn_reply(async move {
+                            response.await.ok().map(JsonRpcMessage::Response)
+                        });
                     }
                 }
-                JsonRpcMessage::Notification(json_rpc_notification) => {
-                    let handlers = self.notification_handlers.lock().await;
-                    if let Some(handler) = handlers.get(&json_rpc_notification.method) {
-                        handler.handle(json_rpc_notification)?;
-                    }
+            }
+        }
+        debug!("Transport closed, stop listening");
+        Ok(())
+    }
+
+    /// Handle a single message
+    /// returns a receiver for the response if the message is a request
+    async fn handle_message(
+        &self,
+        message: JsonRpcMessage,
+    ) -> Result<Option<oneshot::Receiver<JsonRpcResponse>>> {
+        match message {
+            JsonRpcMessage::Request(request) => return Ok(Some(self.spawn_request(request))),
+            JsonRpcMessage::Response(response) => {
+                // Remove and send response through the channel
+                let mut pending = self.pending_requests.lock().await;
+                // Store the result of remove in a local variable first to control drop order
+                let tx_opt = pending.remove(&response.id);
+                if let Some(tx) = tx_opt {
+                    // If send fails, the receiver was dropped, just continue
+                    let _ = tx.send(response);
                 }
             }
+            JsonRpcMessage::Notification(json_rpc_notification)
+                if json_rpc_notification.method == "notifications/cancelled" =>
+            {
+                self.handle_cancelled(json_rpc_notification)
+            }
+            JsonRpcMessage::Notification(json_rpc_notification)
+                if json_rpc_notification.method == "notifications/progress"
+                    && self.handle_progress(&json_rpc_notification) => {}
+            JsonRpcMessage::Notification(json_rpc_notification) => {
+                self.spawn_notification(json_rpc_notification)
+            }
+            JsonRpcMessage::Batch(_) => {
+                // batches can not be nested
+                let (tx, rx) = oneshot::channel();
+                let _ = tx.send(JsonRpcResponse {
+                    id: RequestId::Null,
+                    error: Some(JsonRpcError {
+                        code: ErrorCode::InvalidRequest as i32,
+                        message: "Nested batch".to_string(),
+                        data: None,
+                    }),
+                    ..Default::default()
+                });
+                return Ok(Some(rx));
+            }
         }
+        Ok(None)
     }
 
-    async fn handle_request(&self, request: JsonRpcRequest) -> Result<()> {
-        let handlers = self.request_handlers.lock().await;
-        match handlers.get(&request.method) {
-            Some(handler) => match handler.handle(request.clone()).await {
-                Ok(response) => {
-                    let msg = JsonRpcMessage::Response(response);
-                    self.transport.send(&msg)?;
+    /// Dispatch each message of a batch and reply with a batch of the responses
+    /// each invalid element gets its own error response, the others are still handled
+    /// nothing is sent back if the batch only holds notifications and responses
+    async fn handle_batch(&self, elements: Vec<serde_json::Value>) -> Result<()> {
+        if elements.is_empty() {
+            let response = JsonRpcResponse {
+                id: RequestId::Null,
+                error: Some(JsonRpcError {
+                    code: ErrorCode::InvalidRequest as i32,
+                    message: "Empty batch".to_string(),
+                    data: None,
+                }),
+                ..Default::default()
+            };
+            self.transport
+                .send(&JsonRpcMessage::Response(response))
+                .await
+                .map_err(McpError::Transport)?;
+            return Ok(());
+        }
+        let mut pending = Vec::new();
+        for element in elements {
+            match parse_batch_element(element) {
+                Ok(message) => {
+                    if let Some(response) = self.handle_message(message).await? {
+                        pending.push(response);
+                    }
                 }
-                Err(e) => {
-                    let error_response = JsonRpcResponse {
-                        id: request.id,
-                        result: None,
-                        error: Some(JsonRpcError {
-                            code: ErrorCode::InternalError as i32,
-                            message: e.to_string(),
-                            data: None,
-                        }),
-                        ..Default::default()
-                    };
-                    let msg = JsonRpcMessage::Response(error_response);
-                    self.transport.send(&msg)?;
+                Err(error) => {
+                    warn!("Received invalid batch element");
+                    let (tx, rx) = oneshot::channel();
+                    let _ = tx.send(*error);
+                    pending.push(rx);
                 }
-            },
-            _ => {
-                self.transport
-                    .send(&JsonRpcMessage::Response(JsonRpcResponse {
-                        id: request.id,
-                        error: Some(JsonRpcError {
-                            code: ErrorCode::MethodNotFound as i32,
-                            message: format!("Method not found: {}", request.method),
-                            data: None,
-                        }),
-                        ..Default::default()
-                    }))?;
             }
         }
+        if pending.is_empty() {
+            return Ok(());
+        }
+        self.spawn_reply(async move {
+            let mut responses = Vec::new();
+            for response in pending {
+                // cancelled requests do not get a response
+                if let Ok(response) = response.await {
+                    responses.push(JsonRpcMessage::Response(response));
+                }
+            }
+            (!responses.is_empty()).then(|| JsonRpcMessage::batch(responses))
+        });
         Ok(())
     }
+
+    /// Wait for a reply off the listen loop and send it if there is one
+    fn spawn_reply(&self, reply: impl Future<Output = Option<JsonRpcMessage>> + Send + 'static) {
+        let transport = self.transport.clone();
+        tokio::spawn(async move {
+            let Some(message) = reply.await else {
+                return;
+            };
+            if let Err(e) = transport.send(&message).await {
+                warn!("Failed to send response: {e}");
+            }
+        });
+    }
+
+    /// Run the request handler on its own task so that slow handlers
+    /// do not block the listen loop from reading other messages
+    /// the receiver errors if the request was cancelled
+    fn spawn_request(&self, request: JsonRpcRequest) -> oneshot::Receiver<JsonRpcResponse> {
+        let protocol = self.clone();
+        let id = request.id.clone();
+        let cancellation = CancellationToken::new();
+        let token = cancellation.clone();
+        let (tx, rx) = oneshot::channel();
+        // hold the lock while spawning so the task cannot finish before it is tracked
+        let mut in_flight = self.in_flight.lock().unwrap();
+        let handle = tokio::spawn(async move {
+            let Ok(_permit) = protocol.request_permits.clone().acquire_owned().await else {
+                return;
+            };
+            let response = protocol.handle_request(request, token).await;
+            // no longer tracked means the peer cancelled it, the response must not be sent
+            if protocol
+                .in_flight
+                .lock()
+                .unwrap()
+                .remove(&response.id)
+                .is_none()
+            {
+                return;
+            }
+            let _ = tx.send(response);
+        });
+        in_flight.insert(
+            id,
+            InFlightRequest {
+                cancellation,
+                abort: handle.abort_handle(),
+            },
+        );
+        rx
+    }
+
+    /// Run the notification handler on its own task, like requests,
+    /// so a handler can wait for responses to its own requests to the peer
+    fn spawn_notification(&self, notification: JsonRpcNotification) {
+        if !self
+            .notification_handlers
+            .contains_key(&notification.method)
+        {
+            return;
+        }
+        let protocol = self.clone();
+        tokio::spawn(async move {
+            let Ok(_permit) = protocol.request_permits.clone().acquire_owned().await else {
+                return;
+            };
+            let method = notification.method.clone();
+            let handler = &protocol.notification_handlers[&method];
+            let ctx = NotificationContext::new(Arc::new(protocol.clone()));
+            if let Err(e) = handler.handle(notification, ctx).await {
+                warn!("Notification handler for {method} failed: {e}");
+            }
+        });
+    }
+
+    /// Forward progress of one of our requests to its callback
+    /// returns false if no callback is registered for the token
+    fn handle_progress(&self, notification: &JsonRpcNotification) -> bool {
+        let params = notification.params.clone().unwrap_or_default();
+        let Ok(progress) = serde_json::from_value::<ProgressNotification>(params) else {
+            warn!("Invalid progress notification");
+            return false;
+        };
+        let handler = self
+            .progress_callbacks
+            .lock()
+            .unwrap()
+            .get(&progress.progress_token)
+            .cloned();
+        match handler {
+            Some(handler) => {
+                handler.reset.notify_one();
+                (handler.callback)(progress);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Abort the handler of a request cancelled by the peer
+    fn handle_cancelled(&self, notification: JsonRpcNotification) {
+        let params = notification.params.unwrap_or_default();
+        let Ok(cancelled) = serde_json::from_value::<CancelledNotification>(params) else {
+            warn!("Invalid cancellation notification");
+            return;
+        };
+        let Some(request) = self.in_flight.lock().unwrap().remove(&cancelled.request_id) else {
+            // already completed or unknown, nothing to do
+            return;
+        };
+        debug!(
+            "Request {} cancelled: {}",
+            cancelled.request_id,
+            cancelled.reason.as_deref().unwrap_or("no reason")
+        );
+        request.cancellation.cancel();
+        request.abort.abort();
+    }
+
+    async fn handle_request(
+        &self,
+        request: JsonRpcRequest,
+        cancellation: CancellationToken,
+    ) -> JsonRpcResponse {
+        let Some(handler) = self.request_handlers.get(&request.method) else {
+            return JsonRpcResponse {
+                id: request.id,
+                error: Some(McpError::method_not_found(&request.method).into_json_rpc_error()),
+                ..Default::default()
+            };
+        };
+        let id = request.id.clone();
+        let meta = request
+            .params
+            .as_ref()
+            .and_then(|params| params.get("_meta"))
+            .cloned();
+        let ctx = RequestContext::new(id.clone(), meta, cancellation, Arc::new(self.clone()));
+        match handler.handle(request, ctx).await {
+            Ok(response) => response,
+            // the handler picks the error code and data through `McpError`
+            Err(e) => JsonRpcResponse {
+                id,
+                result: None,
+                error: Some(e.into_json_rpc_error()),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Sends `notifications/cancelled` when a request future is dropped before it completes
+struct CancelOnDrop<T: Transport> {
+    protocol: Option<Protocol<T>>,
+    id: RequestId,
+}
+
+impl<T: Transport> CancelOnDrop<T> {
+    fn disarm(mut self) {
+        self.protocol = None;
+    }
+}
+
+impl<T: Transport> Drop for CancelOnDrop<T> {
+    fn drop(&mut self) {
+        let Some(protocol) = self.protocol.take() else {
+            return;
+        };
+        let id = self.id.clone();
+        if let Ok(handle) = tokio::runtime::Handle::try_current() {
+            handle.spawn(async move { protocol.cancel_request(&id, "Request dropped").await });
+        }
+    }
+}
+
+/// Object safe handle to the peer on the other side of a [`Protocol`]
+/// lets handlers talk back without knowing the transport type
+#[async_trait]
+pub trait Peer: Send + Sync {
+    async fn notify(&self, method: &str, params: Option<serde_json::Value>) -> Result<()>;
+
+    async fn request(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+        options: RequestOptions,
+    ) -> Result<JsonRpcResponse>;
+
+    /// The minimum level of log messages the peer asked for, unset to receive all
+    fn log_level(&self) -> LogLevelFilter {
+        LogLevelFilter::default()
+    }
+
+    /// The capabilities of the peer if it is an initialized client
+    fn client_capabilities(&self) -> Option<ClientCapabilities> {
+        None
+    }
+}
+
+/// Fails unless the client advertised the capability `supported` looks for
+/// shared by the server methods and the request context, `name` is used in the error
+pub(crate) fn check_client_capability(
+    peer: &dyn Peer,
+    name: &str,
+    supported: impl Fn(&ClientCapabilities) -> bool,
+) -> Result<()> {
+    if peer.client_capabilities().as_ref().is_some_and(supported) {
+        return Ok(());
+    }
+    Err(McpError::Protocol(format!(
+        "Client does not support {name}"
+    )))
+}
+
+/// The capabilities a client sent in `initialize`, shared by a connection
+#[derive(Clone, Default)]
+pub struct SharedClientCapabilities(Arc<std::sync::RwLock<Option<ClientCapabilities>>>);
+
+impl SharedClientCapabilities {
+    pub fn get(&self) -> Option<ClientCapabilities> {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn set(&self, capabilities: ClientCapabilities) {
+        *self.0.write().unwrap() = Some(capabilities);
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Peer for Protocol<T> {
+    async fn notify(&self, method: &str, params: Option<serde_json::Value>) -> Result<()> {
+        Protocol::notify(self, method, params).await
+    }
+
+    async fn request(
+        &self,
+        method: &str,
+        params: Option<serde_json::Value>,
+        options: RequestOptions,
+    ) -> Result<JsonRpcResponse> {
+        Protocol::request(self, method, params, options).await
+    }
+
+    fn log_level(&self) -> LogLevelFilter {
+        self.log_level.clone()
+    }
+
+    fn client_capabilities(&self) -> Option<ClientCapabilities> {
+        self.client_capabilities.get()
+    }
 }
 
+/// Map a transport error caused by malformed input to the JSON-RPC error response to reply with
+/// the response keeps the id of messages parsed with `parse_message` when it can be read,
+/// returns `None` for other errors such as I/O failures
+fn malformed_message_response(e: &anyhow::Error) -> Option<JsonRpcResponse> {
+    if let Some(InvalidMessage(response)) = e.downcast_ref::<InvalidMessage>() {
+        return Some(*response.clone());
+    }
+    let (code, message) = if let Some(e) = e.downcast_ref::<serde_json::Error>() {
+        match e.classify() {
+            // valid json that is not a valid message
+            serde_json::error::Category::Data => (ErrorCode::InvalidRequest, "Invalid Request"),
+            serde_json::error::Category::Syntax | serde_json::error::Category::Eof => {
+                (ErrorCode::ParseError, "Parse error")
+            }
+            serde_json::error::Category::Io => return None,
+        }
+    } else if e.downcast_ref::<std::string::FromUtf8Error>().is_some() {
+        (ErrorCode::ParseError, "Parse error")
+    } else {
+        return None;
+    };
+    Some(JsonRpcResponse {
+        id: RequestId::Null,
+        error: Some(JsonRpcError {
+            code: code as i32,
+            message: message.to_string(),
+            data: Some(serde_json::Value::String(e.to_string())),
+        }),
+        ..Default::default()
+    })
+}
+
+/// Progress token used for our own request `id`
+fn progress_token(id: &RequestId) -> ProgressToken {
+    match id {
+        RequestId::Number(n) => ProgressToken::Number(*n),
+        id => ProgressToken::String(id.to_string()),
+    }
+}
+
+/// Add `_meta.progressToken` to the request params
+fn with_progress_token(
+    params: Option<serde_json::Value>,
+    token: &ProgressToken,
+) -> Result<serde_json::Value> {
+    let mut params = params.unwrap_or_else(|| serde_json::json!({}));
+    let object = params
+        .as_object_mut()
+        .ok_or_else(|| McpError::invalid_params("Progress requires params to be an object"))?;
+    let meta = object
+        .entry("_meta")
+        .or_insert_with(|| serde_json::json!({}));
+    let meta = meta
+        .as_object_mut()
+        .ok_or_else(|| McpError::invalid_params("Request `_meta` must be an object"))?;
+    meta.insert("progressToken".to_string(), serde_json::to_value(token)?);
+    Ok(params)
+}
+
+/// Called with each progress notification the peer sends for a request
+pub type ProgressCallback = Arc<dyn Fn(ProgressNotification) + Send + Sync>;
+
 /// The default request timeout, in milliseconds
 pub const DEFAULT_REQUEST_TIMEOUT_MSEC: u64 = 60000;
+#[derive(Clone)]
 pub struct RequestOptions {
     timeout: Duration,
+    max_total_timeout: Option<Duration>,
+    progress: Option<ProgressCallback>,
+    cancellation: CancellationToken,
 }
 
 impl RequestOptions {
+    /// Time to wait for the response, restarted by each progress notification of the request
     pub fn timeout(self, timeout: Duration) -> Self {
-        Self { timeout }
+        Self { timeout, ..self }
+    }
+
+    /// Upper bound on the time to wait for the response, however much progress is reported
+    pub fn max_total_timeout(self, max_total_timeout: Duration) -> Self {
+        Self {
+            max_total_timeout: Some(max_total_timeout),
+            ..self
+        }
+    }
+
+    /// Cancel the request once `token` is cancelled
+    /// the peer is told to stop and the request fails with [`McpError::Cancelled`]
+    pub fn cancellation(self, cancellation: CancellationToken) -> Self {
+        Self {
+            cancellation,
+            ..self
+        }
+    }
+
+    /// Ask the peer for progress, `callback` is called for each progress notification
+    pub fn on_progress(
+        self,
+        callback: impl Fn(ProgressNotification) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            progress: Some(Arc::new(callback)),
+            ..self
+        }
     }
 }
 
@@ -169,14 +714,23 @@ impl Default for RequestOptions {
     fn default() -> Self {
         Self {
             timeout: Duration::from_millis(DEFAULT_REQUEST_TIMEOUT_MSEC),
+            max_total_timeout: None,
+            progress: None,
+            cancellation: CancellationToken::new(),
         }
     }
 }
 
+/// The default number of requests handled concurrently
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 64;
+
 pub struct ProtocolBuilder<T: Transport> {
     transport: T,
-    request_handlers: HashMap<String, Box<dyn RequestHandler>>,
+    request_handlers: HashMap<String, Arc<dyn RequestHandler>>,
     notification_handlers: HashMap<String, Box<dyn NotificationHandler>>,
+    max_concurrent_requests: usize,
+    log_level: LogLevelFilter,
+    client_capabilities: SharedClientCapabilities,
 }
 impl<T: Transport> ProtocolBuilder<T> {
     pub fn new(transport: T) -> Self {
@@ -184,17 +738,66 @@ impl<T: Transport> ProtocolBuilder<T> {
             transport,
             request_handlers: HashMap::new(),
             notification_handlers: HashMap::new(),
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+            log_level: LogLevelFilter::default(),
+            client_capabilities: SharedClientCapabilities::default(),
         }
     }
+
+    /// The log level filter of the protocol being built, lets handlers change it
+    pub fn log_level(&self) -> LogLevelFilter {
+        self.log_level.clone()
+    }
+
+    /// The client capabilities of the protocol being built, set by the `initialize` handler
+    pub fn client_capabilities(&self) -> SharedClientCapabilities {
+        self.client_capabilities.clone()
+    }
+
+    /// Set the maximum number of request handlers running at the same time
+    /// further requests wait until a running handler completes
+    /// at least one request is handled at a time, `0` is treated as `1`
+    pub fn max_concurrent_requests(mut self, max: usize) -> Self {
+        self.max_concurrent_requests = max.max(1);
+        self
+    }
     /// Register a typed request handler
     pub fn request_handler<Req, Resp>(
-        mut self,
+        self,
         method: &str,
         handler: impl Fn(Req) -> Result<Resp> + Send + Sync + 'static,
     ) -> Self
     where
         Req: DeserializeOwned + Send + Sync + 'static,
         Resp: Serialize + Send + Sync + 'static,
+    {
+        self.async_request_handler(method, move |req| std::future::ready(handler(req)))
+    }
+
+    /// Register a typed async request handler
+    pub fn async_request_handler<Req, Resp, Fut>(
+        self,
+        method: &str,
+        handler: impl Fn(Req) -> Fut + Send + Sync + 'static,
+    ) -> Self
+    where
+        Req: DeserializeOwned + Send + Sync + 'static,
+        Resp: Serialize + Send + Sync + 'static,
+        Fut: Future<Output = Result<Resp>> + Send + 'static,
+    {
+        self.context_request_handler(method, move |req, _ctx| handler(req))
+    }
+
+    /// Register a typed async request handler that also receives the [`RequestContext`]
+    pub fn context_request_handler<Req, Resp, Fut>(
+        mut self,
+        method: &str,
+        handler: impl Fn(Req, RequestContext) -> Fut + Send + Sync + 'static,
+    ) -> Self
+    where
+        Req: DeserializeOwned + Send + Sync + 'static,
+        Resp: Serialize + Send + Sync + 'static,
+        Fut: Future<Output = Result<Resp>> + Send + 'static,
     {
         let handler = TypedRequestHandler {
             handler,
@@ -202,21 +805,60 @@ impl<T: Transport> ProtocolBuilder<T> {
         };
 
         self.request_handlers
-            .insert(method.to_string(), Box::new(handler));
+            .insert(method.to_string(), Arc::new(handler));
         self
     }
 
+    /// Register an async request handler working on the raw json params and result
+    /// params is `None` when the request has no params
+    pub fn raw_request_handler<Fut>(
+        self,
+        method: &str,
+        handler: impl Fn(Option<serde_json::Value>) -> Fut + Send + Sync + 'static,
+    ) -> Self
+    where
+        Fut: Future<Output = Result<serde_json::Value>> + Send + 'static,
+    {
+        self.async_request_handler(method, handler)
+    }
+
     pub fn has_request_handler(&self, method: &str) -> bool {
         self.request_handlers.contains_key(method)
     }
 
     pub fn notification_handler<N>(
-        mut self,
+        self,
         method: &str,
         handler: impl Fn(N) -> Result<()> + Send + Sync + 'static,
     ) -> Self
     where
         N: DeserializeOwned + Send + Sync + 'static,
+    {
+        self.async_notification_handler(method, move |n| std::future::ready(handler(n)))
+    }
+
+    /// Register a typed async notification handler
+    pub fn async_notification_handler<N, Fut>(
+        self,
+        method: &str,
+        handler: impl Fn(N) -> Fut + Send + Sync + 'static,
+    ) -> Self
+    where
+        N: DeserializeOwned + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.context_notification_handler(method, move |n, _ctx| handler(n))
+    }
+
+    /// Register a typed async notification handler that also receives the [`NotificationContext`]
+    pub fn context_notification_handler<N, Fut>(
+        mut self,
+        method: &str,
+        handler: impl Fn(N, NotificationContext) -> Fut + Send + Sync + 'static,
+    ) -> Self
+    where
+        N: DeserializeOwned + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
     {
         self.notification_handlers.insert(
             method.to_string(),
@@ -228,11 +870,32 @@ impl<T: Transport> ProtocolBuilder<T> {
         self
     }
 
-    pub fn build(self) -> Protocol<T> {
+    /// Register an async notification handler working on the raw json params
+    pub fn raw_notification_handler<Fut>(
+        self,
+        method: &str,
+        handler: impl Fn(Option<serde_json::Value>) -> Fut + Send + Sync + 'static,
+    ) -> Self
+    where
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.async_notification_handler(method, handler)
+    }
+
+    pub fn build(mut self) -> Protocol<T> {
+        // answer pings unless the user registered their own handler
+        if !self.has_request_handler("ping") {
+            self = self.request_handler("ping", |_: serde_json::Value| Ok(serde_json::json!({})));
+        }
         Protocol {
             transport: Arc::new(self.transport),
-            request_handlers: Arc::new(Mutex::new(self.request_handlers)),
-            notification_handlers: Arc::new(Mutex::new(self.notification_handlers)),
+            request_handlers: Arc::new(self.request_handlers),
+            notification_handlers: Arc::new(self.notification_handlers),
+            request_permits: Arc::new(Semaphore::new(self.max_concurrent_requests)),
+            in_flight: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            progress_callbacks: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            log_level: self.log_level,
+            client_capabilities: self.client_capabilities,
             request_id: Arc::new(AtomicU64::new(0)),
             pending_requests: Arc::new(Mutex::new(HashMap::new())),
         }
@@ -242,40 +905,50 @@ impl<T: Transport> ProtocolBuilder<T> {
 // Wrapper for handler types using async trait
 #[async_trait]
 trait RequestHandler: Send + Sync {
-    async fn handle(&self, request: JsonRpcRequest) -> Result<JsonRpcResponse>;
+    async fn handle(&self, request: JsonRpcRequest, ctx: RequestContext)
+    -> Result<JsonRpcResponse>;
 }
 
+#[async_trait]
 trait NotificationHandler: Send + Sync {
-    fn handle(&self, notification: JsonRpcNotification) -> Result<()>;
+    async fn handle(
+        &self,
+        notification: JsonRpcNotification,
+        ctx: NotificationContext,
+    ) -> Result<()>;
 }
 
 // Typed handler implementations
-struct TypedRequestHandler<Req, Resp, F>
+struct TypedRequestHandler<Req, Resp, F, Fut>
 where
     Req: DeserializeOwned + Send + Sync + 'static,
     Resp: Serialize + Send + Sync + 'static,
-    F: Fn(Req) -> Result<Resp> + Send + Sync + 'static,
+    F: Fn(Req, RequestContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Resp>> + Send + 'static,
 {
     handler: F,
-    _phantom: std::marker::PhantomData<(Req, Resp)>,
+    _phantom: std::marker::PhantomData<fn(Req) -> Fut>,
 }
 
 #[async_trait]
-impl<Req, Resp, F> RequestHandler for TypedRequestHandler<Req, Resp, F>
+impl<Req, Resp, F, Fut> RequestHandler for TypedRequestHandler<Req, Resp, F, Fut>
 where
     Req: DeserializeOwned + Send + Sync + 'static,
     Resp: Serialize + Send + Sync + 'static,
-    F: Fn(Req) -> Result<Resp> + Send + Sync + 'static,
+    F: Fn(Req, RequestContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Resp>> + Send + 'static,
 {
-    async fn handle(&self, request: JsonRpcRequest) -> Result<JsonRpcResponse> {
+    async fn handle(
+        &self,
+        request: JsonRpcRequest,
+        ctx: RequestContext,
+    ) -> Result<JsonRpcResponse> {
         // If params is None or null, deserialize as unit type using Value::Null
-        let params: Req = if request.params.is_none() || request.params.as_ref().unwrap().is_null()
-        {
-            serde_json::from_value(serde_json::Value::Null)?
-        } else {
-            serde_json::from_value(request.params.unwrap())?
+        let params: Req = match serde_json::from_value(request.params.unwrap_or_default()) {
+            Ok(params) => params,
+            Err(e) => return Err(McpError::invalid_params(format!("Invalid params: {e}"))),
         };
-        let result = (self.handler)(params)?;
+        let result = (self.handler)(params, ctx).await?;
         Ok(JsonRpcResponse {
             id: request.id,
             result: Some(serde_json::to_value(result)?),
@@ -284,28 +957,772 @@ where
         })
     }
 }
-pub struct TypedNotificationHandler<N, F>
+pub struct TypedNotificationHandler<N, F, Fut>
 where
     N: DeserializeOwned + Send + Sync + 'static,
-    F: Fn(N) -> Result<()> + Send + Sync + 'static,
+    F: Fn(N, NotificationContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
 {
     handler: F,
-    _phantom: std::marker::PhantomData<N>,
+    _phantom: std::marker::PhantomData<fn(N) -> Fut>,
 }
 
 #[async_trait]
-impl<N, F> NotificationHandler for TypedNotificationHandler<N, F>
+impl<N, F, Fut> NotificationHandler for TypedNotificationHandler<N, F, Fut>
 where
     N: DeserializeOwned + Send + Sync + 'static,
-    F: Fn(N) -> Result<()> + Send + Sync + 'static,
+    F: Fn(N, NotificationContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
 {
-    fn handle(&self, notification: JsonRpcNotification) -> Result<()> {
-        let params: N =
-            if notification.params.is_none() || notification.params.as_ref().unwrap().is_null() {
-                serde_json::from_value(serde_json::Value::Null)?
-            } else {
-                serde_json::from_value(notification.params.unwrap())?
+    async fn handle(
+        &self,
+        notification: JsonRpcNotification,
+        ctx: NotificationContext,
+    ) -> Result<()> {
+        let params: N = serde_json::from_value(notification.params.unwrap_or_default())?;
+        (self.handler)(params, ctx).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::{Message, parse_message};
+    use crate::types::LoggingLevel;
+    use anyhow::Result;
+    use tokio::sync::mpsc;
+
+    /// Transport backed by channels, the test drives the other side
+    /// incoming messages are sent as json text so tests can send malformed input
+    struct ChannelTransport {
+        incoming: Mutex<mpsc::UnboundedReceiver<String>>,
+        outgoing: mpsc::UnboundedSender<Message>,
+    }
+
+    #[async_trait]
+    impl Transport for ChannelTransport {
+        async fn send(&self, message: &Message) -> Result<()> {
+            self.outgoing.send(message.clone())?;
+            Ok(())
+        }
+
+        async fn receive(&self) -> Result<Option<Message>> {
+            let Some(line) = self.incoming.lock().await.recv().await else {
+                return Ok(None);
+            };
+            Ok(Some(parse_message(&line)?))
+        }
+
+        async fn open(&self) -> Result<()> {
+            Ok(())
+        }
+
+        async fn close(&self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    struct PeerSender(mpsc::UnboundedSender<String>);
+
+    impl PeerSender {
+        fn send(&self, message: Message) -> Result<()> {
+            self.send_raw(&serde_json::to_string(&message)?)
+        }
+
+        fn send_raw(&self, line: &str) -> Result<()> {
+            self.0.send(line.to_string())?;
+            Ok(())
+        }
+    }
+
+    fn channel_transport() -> (
+        ChannelTransport,
+        PeerSender,
+        mpsc::UnboundedReceiver<Message>,
+    ) {
+        let (in_tx, in_rx) = mpsc::unbounded_channel();
+        let (out_tx, out_rx) = mpsc::unbounded_channel();
+        let transport = ChannelTransport {
+            incoming: Mutex::new(in_rx),
+            outgoing: out_tx,
+        };
+        (transport, PeerSender(in_tx), out_rx)
+    }
+
+    fn request(id: i64, method: &str) -> Message {
+        JsonRpcMessage::Request(JsonRpcRequest {
+            id: id.into(),
+            method: method.to_string(),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_slow_request_does_not_block_others() -> Result<()> {
+        let (transport, tx, mut rx) = channel_transport();
+        let release = Arc::new(tokio::sync::Notify::new());
+        let protocol = Protocol::builder(transport)
+            .async_request_handler("slow", {
+                let release = release.clone();
+                move |_: ()| {
+                    let release = release.clone();
+                    async move {
+                        release.notified().await;
+                        Ok("slow")
+                    }
+                }
+            })
+            .request_handler("fast", |_: ()| Ok("fast"))
+            .build();
+        tokio::spawn(async move { protocol.listen().await });
+
+        tx.send(request(1, "slow"))?;
+        tx.send(request(2, "fast"))?;
+        let JsonRpcMessage::Response(response) = rx.recv().await.unwrap() else {
+            panic!("Expected Response variant");
+        };
+        assert_eq!(response.id, RequestId::Number(2));
+
+        release.notify_one();
+        let JsonRpcMessage::Response(response) = rx.recv().await.unwrap() else {
+            panic!("Expected Response variant");
+        };
+        assert_eq!(response.id, RequestId::Number(1));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent_requests() -> Result<()> {
+        let (transport, tx, mut rx) = channel_transport();
+        let running = Arc::new(AtomicU64::new(0));
+        let max_running = Arc::new(AtomicU64::new(0));
+        let protocol = Protocol::builder(transport)
+            .max_concurrent_requests(2)
+            .async_request_handler("work", {
+                let running = running.clone();
+                let max_running = max_running.clone();
+                move |_: ()| {
+                    let running = running.clone();
+                    let max_running = max_running.clone();
+                    async move {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_running.fetch_max(now, Ordering::SeqCst);
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                        running.fetch_sub(1, Ordering::SeqCst);
+                        Ok(())
+                    }
+                }
+            })
+            .build();
+        tokio::spawn(async move { protocol.listen().await });
+
+        for id in 0..6 {
+            tx.send(request(id, "work"))?;
+        }
+        for _ in 0..6 {
+            rx.recv().await.unwrap();
+        }
+        assert!(max_running.load(Ordering::SeqCst) <= 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_zero_max_concurrent_requests() -> Result<()> {
+        let (transport, tx, mut rx) = channel_transport();
+        let protocol = Protocol::builder(transport)
+            .max_concurrent_requests(0)
+            .build();
+        tokio::spawn(async move { protocol.listen().await });
+
+        tx.send(request(1, "ping"))?;
+        let JsonRpcMessage::Response(response) = rx.recv().await.unwrap() else {
+            panic!("Expected Response variant");
+        };
+        assert_eq!(response.id, RequestId::Number(1));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_raw_handlers() -> Result<()> {
+        let (transport, tx, mut rx) = channel_transport();
+        let (seen_tx, mut seen_rx) = mpsc::unbounded_channel();
+        let protocol = Protocol::builder(transport)
+            .raw_request_handler("echo", |params| async move {
+                Ok(params.unwrap_or(serde_json::json!("no params")))
+            })
+            .raw_notification_handler("notify", move |params| {
+                let seen_tx = seen_tx.clone();
+                async move {
+                    seen_tx.send(params).map_err(anyhow::Error::from)?;
+                    Ok(())
+                }
+            })
+            .build();
+        tokio::spawn(async move { protocol.listen().await });
+
+        tx.send(request(1, "echo"))?;
+        let JsonRpcMessage::Response(response) = rx.recv().await.unwrap() else {
+            panic!("Expected Response variant");
+        };
+        assert_eq!(response.result, Some(serde_json::json!("no params")));
+
+        tx.send(JsonRpcMessage::Notification(JsonRpcNotification {
+            method: "notify".to_string(),
+            params: Some(serde_json::json!({"a": 1})),
+            ..Default::default()
+        }))?;
+        assert_eq!(
+            seen_rx.recv().await,
+            Some(Some(serde_json::json!({"a": 1})))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_notification_handler_requests_peer() -> Result<()> {
+        let (transport, tx, mut rx) = channel_transport();
+        let (seen_tx, mut seen_rx) = mpsc::unbounded_channel();
+        let protocol = Protocol::builder(transport)
+            .context_notification_handler("changed", move |_: (), ctx: NotificationContext| {
+                let seen_tx = seen_tx.clone();
+                async move {
+                    let response = ctx.peer().request("list", None, Default::default()).await?;
+                    seen_tx.send(response.result).map_err(anyhow::Error::from)?;
+                    Ok(())
+                }
+            })
+            .build();
+        tokio::spawn(async move { protocol.listen().await });
+
+        tx.send(JsonRpcMessage::Notification(JsonRpcNotification {
+            method: "changed".to_string(),
+            ..Default::default()
+        }))?;
+        let JsonRpcMessage::Request(request) = rx.recv().await.unwrap() else {
+            panic!("Expected Request variant");
+        };
+        assert_eq!(request.method, "list");
+        // the response is routed while the notification handler is waiting for it
+        tx.send(JsonRpcMessage::Response(JsonRpcResponse {
+            id: request.id,
+            result: Some(serde_json::json!(["a"])),
+            ..Default::default()
+        }))?;
+        assert_eq!(seen_rx.recv().await, Some(Some(serde_json::json!(["a"]))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_request_context() -> Result<()> {
+        let (transport, tx, mut rx) = channel_transport();
+        let protocol = Protocol::builder(transport)
+            .context_request_handler(
+                "work",
+                |_: serde_json::Value, ctx: RequestContext| async move {
+                    ctx.progress().report(0.5, Some(1.0)).await?;
+                    Ok(ctx.meta().cloned())
+                },
+            )
+            .build();
+        tokio::spawn(async move { protocol.listen().await });
+
+        tx.send(JsonRpcMessage::Request(JsonRpcRequest {
+            id: 7.into(),
+            method: "work".to_string(),
+            params: Some(serde_json::json!({"_meta": {"progressToken": "abc"}})),
+            ..Default::default()
+        }))?;
+        let JsonRpcMessage::Notification(progress) = rx.recv().await.unwrap() else {
+            panic!("Expected Notification variant");
+        };
+        assert_eq!(progress.method, "notifications/progress");
+        assert_eq!(
+            progress.params,
+            Some(serde_json::json!({"progressToken": "abc", "progress": 0.5, "total": 1.0}))
+        );
+        let JsonRpcMessage::Response(response) = rx.recv().await.unwrap() else {
+            panic!("Expected Response variant");
+        };
+        assert_eq!(response.id, RequestId::Number(7));
+        assert_eq!(
+            response.result,
+            Some(serde_json::json!({"progressToken": "abc"}))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_log_level_filters_messages() -> Result<()> {
+        let (transport, tx, mut rx) = channel_transport();
+        let builder = Protocol::builder(transport);
+        builder.log_level().set(LoggingLevel::Warning);
+        let protocol = builder
+            .context_request_handler("work", |_: (), ctx: RequestContext| async move {
+                ctx.log(LoggingLevel::Info, serde_json::json!("dropped"))
+                    .await?;
+                ctx.log(LoggingLevel::Error, serde_json::json!("sent"))
+                    .await?;
+                Ok(())
+            })
+            .build();
+        tokio::spawn(async move { protocol.listen().await });
+
+        tx.send(request(1, "work"))?;
+        let JsonRpcMessage::Notification(message) = rx.recv().await.unwrap() else {
+            panic!("Expected Notification variant");
+        };
+        assert_eq!(message.method, "notifications/message");
+        assert_eq!(
+            message.params,
+            Some(serde_json::json!({"level": "error", "data": "sent"}))
+        );
+        let JsonRpcMessage::Response(response) = rx.recv().await.unwrap() else {
+            panic!("Expected Response variant");
+        };
+        assert_eq!(response.id, RequestId::Number(1));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_request_is_aborted() -> Result<()> {
+        let (transport, tx, mut rx) = channel_transport();
+        let (started_tx, mut started_rx) = mpsc::unbounded_channel();
+        let (dropped_tx, dropped_rx) = oneshot::channel::<()>();
+        let dropped_tx = std::sync::Mutex::new(Some(dropped_tx));
+        let protocol = Protocol::builder(transport)
+            .async_request_handler("slow", move |_: ()| {
+                // signals when the handler future is dropped
+                let guard = dropped_tx.lock().unwrap().take();
+                let started_tx = started_tx.clone();
+                async move {
+                    let _guard = guard;
+                    started_tx.send(()).map_err(anyhow::Error::from)?;
+                    std::future::pending::<crate::error::Result<()>>().await
+                }
+            })
+            .request_handler("fast", |_: ()| Ok("fast"))
+            .build();
+        tokio::spawn(async move { protocol.listen().await });
+
+        tx.send(request(1, "slow"))?;
+        started_rx.recv().await;
+        tx.send(JsonRpcMessage::Notification(JsonRpcNotification {
+            method: "notifications/cancelled".to_string(),
+            params: Some(serde_json::json!({"requestId": 1, "reason": "test"})),
+            ..Default::default()
+        }))?;
+        // the sender is dropped together with the aborted handler
+        assert!(dropped_rx.await.is_err());
+
+        tx.send(request(2, "fast"))?;
+        let JsonRpcMessage::Response(response) = rx.recv().await.unwrap() else {
+            panic!("Expected Response variant");
+        };
+        assert_eq!(response.id, RequestId::Number(2));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_handlers_aborted_when_listen_returns() -> Result<()> {
+        let (transport, tx, _rx) = channel_transport();
+        let (started_tx, mut started_rx) = mpsc::unbounded_channel();
+        let (dropped_tx, dropped_rx) = oneshot::channel::<()>();
+        let dropped_tx = std::sync::Mutex::new(Some(dropped_tx));
+        let protocol = Protocol::builder(transport)
+            .async_request_handler("slow", move |_: ()| {
+                let guard = dropped_tx.lock().unwrap().take();
+                let started_tx = started_tx.clone();
+                async move {
+                    let _guard = guard;
+                    started_tx.send(()).map_err(anyhow::Error::from)?;
+                    std::future::pending::<crate::error::Result<()>>().await
+                }
+            })
+            .build();
+        let listen = tokio::spawn(async move { protocol.listen().await });
+
+        tx.send(request(1, "slow"))?;
+        started_rx.recv().await;
+        // the peer goes away while the handler runs
+        drop(tx);
+        listen.await??;
+        tokio::time::timeout(Duration::from_secs(1), dropped_rx)
+            .await?
+            .ok();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout_sends_cancellation() -> Result<()> {
+        let (transport, _tx, mut rx) = channel_transport();
+        let protocol = Protocol::builder(transport).build();
+
+        let result = protocol
+            .request(
+                "slow",
+                None,
+                RequestOptions::default().timeout(Duration::from_millis(10)),
+            )
+            .await;
+        assert!(result.is_err());
+        assert!(protocol.pending_requests.lock().await.is_empty());
+
+        let JsonRpcMessage::Request(request) = rx.recv().await.unwrap() else {
+            panic!("Expected Request variant");
+        };
+        let JsonRpcMessage::Notification(cancelled) = rx.recv().await.unwrap() else {
+            panic!("Expected Notification variant");
+        };
+        assert_eq!(cancelled.method, "notifications/cancelled");
+        let cancelled: CancelledNotification = serde_json::from_value(cancelled.params.unwrap())?;
+        assert_eq!(cancelled.request_id, request.id);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_request() -> Result<()> {
+        let (transport, _tx, mut rx) = channel_transport();
+        let protocol = Protocol::builder(transport).build();
+
+        let cancellation = CancellationToken::new();
+        let options = RequestOptions::default().cancellation(cancellation.clone());
+        let request = tokio::spawn({
+            let protocol = protocol.clone();
+            async move { protocol.request("slow", None, options).await }
+        });
+        let JsonRpcMessage::Request(sent) = rx.recv().await.unwrap() else {
+            panic!("Expected Request variant");
+        };
+        cancellation.cancel();
+        assert!(matches!(request.await?, Err(McpError::Cancelled)));
+
+        let JsonRpcMessage::Notification(cancelled) = rx.recv().await.unwrap() else {
+            panic!("Expected Notification variant");
+        };
+        let cancelled: CancelledNotification = serde_json::from_value(cancelled.params.unwrap())?;
+        assert_eq!(cancelled.request_id, sent.id);
+        assert!(protocol.pending_requests.lock().await.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dropped_request_sends_cancellation() -> Result<()> {
+        let (transport, _tx, mut rx) = channel_transport();
+        let protocol = Protocol::builder(transport).build();
+
+        let request = protocol.request("slow", None, RequestOptions::default());
+        // drop the request future once the request went out
+        tokio::select! {
+            _ = request => panic!("request should not complete"),
+            message = rx.recv() => assert!(matches!(message, Some(JsonRpcMessage::Request(_)))),
+        }
+
+        let JsonRpcMessage::Notification(cancelled) = rx.recv().await.unwrap() else {
+            panic!("Expected Notification variant");
+        };
+        assert_eq!(cancelled.method, "notifications/cancelled");
+        assert!(protocol.pending_requests.lock().await.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_request_progress_callback() -> Result<()> {
+        let (transport, tx, mut rx) = channel_transport();
+        let protocol = Protocol::builder(transport).build();
+        let listener = protocol.clone();
+        tokio::spawn(async move { listener.listen().await });
+
+        let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
+        let options = RequestOptions::default().on_progress(move |progress| {
+            let _ = progress_tx.send(progress.progress);
+        });
+        let request = tokio::spawn({
+            let protocol = protocol.clone();
+            async move {
+                protocol
+                    .request("work", Some(serde_json::json!({"a": 1})), options)
+                    .await
+            }
+        });
+
+        let JsonRpcMessage::Request(sent) = rx.recv().await.unwrap() else {
+            panic!("Expected Request variant");
+        };
+        let params = sent.params.unwrap();
+        assert_eq!(params["a"], 1);
+        let token = params["_meta"]["progressToken"].clone();
+        for progress in [1, 2] {
+            tx.send(JsonRpcMessage::Notification(JsonRpcNotification {
+                method: "notifications/progress".to_string(),
+                params: Some(serde_json::json!({"progressToken": token, "progress": progress})),
+                ..Default::default()
+            }))?;
+        }
+        tx.send(JsonRpcMessage::Response(JsonRpcResponse {
+            id: sent.id,
+            result: Some(serde_json::json!({})),
+            ..Default::default()
+        }))?;
+
+        request.await??;
+        assert_eq!(progress_rx.recv().await, Some(1.0));
+        assert_eq!(progress_rx.recv().await, Some(2.0));
+        assert!(protocol.progress_callbacks.lock().unwrap().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_progress_resets_timeout() -> Result<()> {
+        let (transport, tx, mut rx) = channel_transport();
+        let protocol = Protocol::builder(transport).build();
+        let listener = protocol.clone();
+        tokio::spawn(async move { listener.listen().await });
+
+        let send_progress = |request: &JsonRpcRequest| {
+            let token = request.params.as_ref().unwrap()["_meta"]["progressToken"].clone();
+            tx.send(JsonRpcMessage::Notification(JsonRpcNotification {
+                method: "notifications/progress".to_string(),
+                params: Some(serde_json::json!({"progressToken": token, "progress": 1})),
+                ..Default::default()
+            }))
+        };
+        let options = RequestOptions::default()
+            .timeout(Duration::from_millis(100))
+            .on_progress(|_| {});
+
+        // progress keeps the request alive past its timeout
+        let request = tokio::spawn({
+            let protocol = protocol.clone();
+            let options = options.clone();
+            async move { protocol.request("work", None, options).await }
+        });
+        let JsonRpcMessage::Request(sent) = rx.recv().await.unwrap() else {
+            panic!("Expected Request variant");
+        };
+        for _ in 0..4 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            send_progress(&sent)?;
+        }
+        tx.send(JsonRpcMessage::Response(JsonRpcResponse {
+            id: sent.id,
+            result: Some(serde_json::json!({})),
+            ..Default::default()
+        }))?;
+        request.await??;
+
+        // but not past the maximum total timeout
+        let options = options.max_total_timeout(Duration::from_millis(150));
+        let request = tokio::spawn({
+            let protocol = protocol.clone();
+            async move { protocol.request("work", None, options).await }
+        });
+        let JsonRpcMessage::Request(sent) = rx.recv().await.unwrap() else {
+            panic!("Expected Request variant");
+        };
+        for _ in 0..4 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            send_progress(&sent)?;
+        }
+        assert!(matches!(request.await?, Err(McpError::Timeout)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_builtin_ping() -> Result<()> {
+        let (transport, tx, mut rx) = channel_transport();
+        let protocol = Protocol::builder(transport).build();
+        tokio::spawn(async move { protocol.listen().await });
+
+        tx.send(request(1, "ping"))?;
+        let JsonRpcMessage::Response(response) = rx.recv().await.unwrap() else {
+            panic!("Expected Response variant");
+        };
+        assert_eq!(response.id, RequestId::Number(1));
+        assert_eq!(response.result, Some(serde_json::json!({})));
+        assert!(response.error.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_string_request_id() -> Result<()> {
+        let (transport, tx, mut rx) = channel_transport();
+        let protocol = Protocol::builder(transport).build();
+        tokio::spawn(async move { protocol.listen().await });
+
+        tx.send(JsonRpcMessage::Request(JsonRpcRequest {
+            id: "req-1".into(),
+            method: "ping".to_string(),
+            ..Default::default()
+        }))?;
+        let JsonRpcMessage::Response(response) = rx.recv().await.unwrap() else {
+            panic!("Expected Response variant");
+        };
+        assert_eq!(response.id, RequestId::from("req-1"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_batch_request() -> Result<()> {
+        let (transport, tx, mut rx) = channel_transport();
+        let protocol = Protocol::builder(transport).build();
+        tokio::spawn(async move { protocol.listen().await });
+
+        tx.send(JsonRpcMessage::batch([
+            request(1, "ping"),
+            JsonRpcMessage::Notification(JsonRpcNotification {
+                method: "notifications/initialized".to_string(),
+                ..Default::default()
+            }),
+            request(2, "missing"),
+        ]))?;
+        let responses = next_batch(&mut rx).await?;
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0].id, RequestId::Number(1));
+        assert_eq!(
+            responses[1].error.as_ref().unwrap().code,
+            ErrorCode::MethodNotFound as i32
+        );
+        Ok(())
+    }
+
+    async fn next_batch(rx: &mut mpsc::UnboundedReceiver<Message>) -> Result<Vec<JsonRpcResponse>> {
+        let JsonRpcMessage::Batch(elements) = rx.recv().await.unwrap() else {
+            panic!("Expected Batch variant");
+        };
+        Ok(elements
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<serde_json::Result<_>>()?)
+    }
+
+    #[tokio::test]
+    async fn test_batch_with_invalid_elements() -> Result<()> {
+        let (transport, tx, mut rx) = channel_transport();
+        let protocol = Protocol::builder(transport).build();
+        tokio::spawn(async move { protocol.listen().await });
+
+        tx.send_raw(
+            r#"[{"jsonrpc":"2.0","id":1,"method":"ping"},{"jsonrpc":"2.0","id":2,"foo":1},3]"#,
+        )?;
+        let responses = next_batch(&mut rx).await?;
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0].id, RequestId::Number(1));
+        assert!(responses[0].error.is_none());
+        for (response, id) in responses[1..]
+            .iter()
+            .zip([RequestId::Number(2), RequestId::Null])
+        {
+            assert_eq!(response.id, id);
+            assert_eq!(
+                response.error.as_ref().unwrap().code,
+                ErrorCode::InvalidRequest as i32
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_batch_edge_cases() -> Result<()> {
+        let (transport, tx, mut rx) = channel_transport();
+        let protocol = Protocol::builder(transport).build();
+        tokio::spawn(async move { protocol.listen().await });
+
+        // an empty batch is answered with a single error
+        tx.send(JsonRpcMessage::Batch(vec![]))?;
+        let JsonRpcMessage::Response(response) = rx.recv().await.unwrap() else {
+            panic!("Expected Response variant");
+        };
+        assert_eq!(response.id, RequestId::Null);
+        assert_eq!(
+            response.error.unwrap().code,
+            ErrorCode::InvalidRequest as i32
+        );
+
+        // a batch of notifications gets no reply at all
+        tx.send(JsonRpcMessage::batch([JsonRpcMessage::Notification(
+            JsonRpcNotification {
+                method: "notifications/initialized".to_string(),
+                ..Default::default()
+            },
+        )]))?;
+        tx.send(request(3, "ping"))?;
+        let JsonRpcMessage::Response(response) = rx.recv().await.unwrap() else {
+            panic!("Expected Response variant");
+        };
+        assert_eq!(response.id, RequestId::Number(3));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_malformed_input_gets_error_response() -> Result<()> {
+        let (transport, tx, mut rx) = channel_transport();
+        let protocol = Protocol::builder(transport)
+            .request_handler("add", |(a, b): (i64, i64)| Ok(a + b))
+            .build();
+        tokio::spawn(async move { protocol.listen().await });
+
+        let mut next_error = async || {
+            let Some(JsonRpcMessage::Response(response)) = rx.recv().await else {
+                panic!("Expected Response variant");
             };
-        (self.handler)(params)
+            (response.id, response.error.unwrap().code)
+        };
+
+        tx.send_raw("{not json")?;
+        assert_eq!(
+            next_error().await,
+            (RequestId::Null, ErrorCode::ParseError as i32)
+        );
+
+        tx.send_raw(r#"{"hello":"world"}"#)?;
+        assert_eq!(
+            next_error().await,
+            (RequestId::Null, ErrorCode::InvalidRequest as i32)
+        );
+
+        // the id is echoed when it can be read
+        tx.send_raw(r#"{"jsonrpc":"2.0","id":3,"method":5}"#)?;
+        assert_eq!(
+            next_error().await,
+            (RequestId::Number(3), ErrorCode::InvalidRequest as i32)
+        );
+
+        tx.send_raw(r#"{"jsonrpc":"2.0","id":1,"method":"add","params":{"a":"x"}}"#)?;
+        assert_eq!(
+            next_error().await,
+            (RequestId::Number(1), ErrorCode::InvalidParams as i32)
+        );
+
+        // the listen loop is still running
+        tx.send(request(2, "ping"))?;
+        let Some(JsonRpcMessage::Response(response)) = rx.recv().await else {
+            panic!("Expected Response variant");
+        };
+        assert_eq!(response.id, RequestId::Number(2));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_handler_error_code_and_data() -> Result<()> {
+        let (transport, tx, mut rx) = channel_transport();
+        let protocol = Protocol::builder(transport)
+            .request_handler("fail", |_: ()| -> crate::error::Result<()> {
+                Err(McpError::rpc(-32000, "app error")
+                    .with_data(serde_json::json!({"retry": true})))
+            })
+            .build();
+        tokio::spawn(async move { protocol.listen().await });
+
+        tx.send(request(1, "fail"))?;
+        let Some(JsonRpcMessage::Response(response)) = rx.recv().await else {
+            panic!("Expected Response variant");
+        };
+        let Err(McpError::Rpc(error)) = response.into_result() else {
+            panic!("Expected Rpc error");
+        };
+        assert_eq!(error.code, -32000);
+        assert_eq!(error.message, "app error");
+        assert_eq!(error.data, Some(serde_json::json!({"retry": true})));
+        Ok(())
     }
 }
@@ -0,0 +1,372 @@
+use crate::context::RequestContext;
+use crate::error::{McpError, Result};
+use crate::types::{
+    Completion, CompletionArgument, ListRequest, ListResourceTemplatesResponse,
+    ReadResourceRequest, ReadResourceResponse, Resource, ResourceContents, ResourceTemplate,
+    ResourcesListResponse,
+};
+use async_trait::async_trait;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+use url::Url;
+
+#[async_trait]
+pub trait ResourceProvider: Send + Sync + 'static {
+    fn uri(&self) -> Url;
+    fn name(&self) -> String;
+    fn description(&self) -> Option<String> {
+        None
+    }
+    fn mime_type(&self) -> Option<String> {
+        None
+    }
+    /// Read the current contents of the resource
+    async fn read(&self, ctx: RequestContext) -> Result<Vec<ResourceContents>>;
+    fn as_definition(&self) -> Resource {
+        Resource {
+            uri: self.uri(),
+            name: self.name(),
+            description: self.description(),
+            mime_type: self.mime_type(),
+        }
+    }
+}
+
+/// A family of resources addressed by an RFC 6570 URI template such as `db://{table}/{id}`
+/// used for resources that are too many to enumerate in `resources/list`
+#[async_trait]
+pub trait ResourceTemplateProvider: Send + Sync + 'static {
+    fn uri_template(&self) -> String;
+    fn name(&self) -> String;
+    fn description(&self) -> Option<String> {
+        None
+    }
+    fn mime_type(&self) -> Option<String> {
+        None
+    }
+    /// Read the resource at `uri`, `variables` holds the values extracted from the template
+    async fn read(
+        &self,
+        uri: Url,
+        variables: HashMap<String, String>,
+        ctx: RequestContext,
+    ) -> Result<Vec<ResourceContents>>;
+    /// Suggest values for the template `variable` starting from the partial `value`
+    /// returns no suggestions by default
+    async fn complete(
+        &self,
+        _variable: &str,
+        _value: &str,
+        _ctx: RequestContext,
+    ) -> Result<Completion> {
+        Ok(Completion::default())
+    }
+    fn as_definition(&self) -> ResourceTemplate {
+        ResourceTemplate {
+            uri_template: self.uri_template(),
+            name: self.name(),
+            description: self.description(),
+            mime_type: self.mime_type(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Resources {
+    // ordered by uri so that cursors stay valid between pages
+    resources: BTreeMap<String, Arc<dyn ResourceProvider>>,
+    // matched in registration order
+    templates: Vec<(UriTemplate, Arc<dyn ResourceTemplateProvider>)>,
+    page_size: Option<usize>,
+}
+
+impl Resources {
+    pub fn add_resource(&mut self, resource: impl ResourceProvider) {
+        self.resources
+            .insert(resource.uri().to_string(), Arc::new(resource));
+    }
+
+    /// Register a resource template, fails if its URI template is invalid
+    pub fn add_resource_template(&mut self, template: impl ResourceTemplateProvider) -> Result<()> {
+        let uri_template = UriTemplate::parse(&template.uri_template())?;
+        self.templates.push((uri_template, Arc::new(template)));
+        Ok(())
+    }
+
+    /// Split `resources/list` responses into pages of at most `page_size` resources
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = Some(page_size.max(1));
+        self
+    }
+
+    pub fn list_resources(&self, request: ListRequest) -> Result<ResourcesListResponse> {
+        let (resources, next_cursor) = paginate(
+            self.resources
+                .values()
+                .map(|resource| resource.as_definition()),
+            request.cursor.as_deref(),
+            self.page_size,
+        )?;
+        Ok(ResourcesListResponse {
+            resources,
+            next_cursor,
+            meta: None,
+        })
+    }
+
+    pub fn list_resource_templates(
+        &self,
+        request: ListRequest,
+    ) -> Result<ListResourceTemplatesResponse> {
+        let (resource_templates, next_cursor) = paginate(
+            self.templates
+                .iter()
+                .map(|(_, template)| template.as_definition()),
+            request.cursor.as_deref(),
+            self.page_size,
+        )?;
+        Ok(ListResourceTemplatesResponse {
+            resource_templates,
+            next_cursor,
+            meta: None,
+        })
+    }
+
+    /// Complete a variable of the resource template registered as `uri_template`
+    pub async fn complete(
+        &self,
+        uri_template: &str,
+        argument: CompletionArgument,
+        ctx: RequestContext,
+    ) -> Result<Completion> {
+        let Some((_, template)) = self
+            .templates
+            .iter()
+            .find(|(_, template)| template.uri_template() == uri_template)
+        else {
+            return Err(McpError::invalid_params(format!(
+                "Unknown resource template: {uri_template}"
+            )));
+        };
+        template
+            .complete(&argument.name, &argument.value, ctx)
+            .await
+    }
+
+    /// Read a listed resource, or else the first template matching the uri
+    pub async fn read_resource(
+        &self,
+        request: ReadResourceRequest,
+        ctx: RequestContext,
+    ) -> Result<ReadResourceResponse> {
+        let contents = if let Some(resource) = self.resources.get(request.uri.as_str()) {
+            resource.read(ctx).await?
+        } else {
+            let Some((variables, template)) =
+                self.templates.iter().find_map(|(uri_template, template)| {
+                    Some((uri_template.match_uri(request.uri.as_str())?, template))
+                })
+            else {
+                return Err(McpError::resource_not_found(request.uri.as_str()));
+            };
+            template.read(request.uri, variables, ctx).await?
+        };
+        Ok(ReadResourceResponse {
+            contents,
+            meta: None,
+        })
+    }
+}
+
+/// A parsed RFC 6570 URI template used to match concrete URIs
+/// supports `{var}` which matches a single path segment and
+/// `{+var}` / `{#var}` which match any characters including `/`
+#[derive(Debug, Clone)]
+pub struct UriTemplate {
+    parts: Vec<TemplatePart>,
+}
+
+#[derive(Debug, Clone)]
+enum TemplatePart {
+    Literal(String),
+    Variable { name: String, reserved: bool },
+}
+
+impl UriTemplate {
+    pub fn parse(template: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            McpError::invalid_params(format!("Invalid URI template `{template}`: {reason}"))
+        };
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(TemplatePart::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| invalid("unclosed `{`"))?
+                + start;
+            let expression = &rest[start + 1..end];
+            let (name, reserved) = match expression.strip_prefix(['+', '#']) {
+                Some(name) => (name, true),
+                None => (expression, false),
+            };
+            if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                return Err(invalid("unsupported expression"));
+            }
+            if let Some(TemplatePart::Variable { .. }) = parts.last() {
+                return Err(invalid("adjacent variables are ambiguous"));
+            }
+            if reserved && expression.starts_with('#') {
+                parts.push(TemplatePart::Literal("#".to_string()));
+            }
+            parts.push(TemplatePart::Variable {
+                name: name.to_string(),
+                reserved,
+            });
+            rest = &rest[end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Literal(rest.to_string()));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Match `uri` against the template and return the extracted variables
+    ///
+    /// Each variable takes the shortest value followed by the next literal, or the rest of
+    /// the uri before a trailing literal. A single forward scan, so matching client supplied
+    /// uris stays linear in their length.
+    pub fn match_uri(&self, uri: &str) -> Option<HashMap<String, String>> {
+        let mut variables = HashMap::new();
+        let mut rest = uri;
+        for (i, part) in self.parts.iter().enumerate() {
+            match part {
+                TemplatePart::Literal(literal) => rest = rest.strip_prefix(literal.as_str())?,
+                TemplatePart::Variable { name, reserved } => {
+                    // simple variables stop at the first reserved delimiter
+                    let max = if *reserved {
+                        rest.len()
+                    } else {
+                        rest.find(['/', '?', '#', '&', ',']).unwrap_or(rest.len())
+                    };
+                    let end = match self.parts.get(i + 1) {
+                        None => rest.len(),
+                        Some(TemplatePart::Literal(literal)) if i + 2 == self.parts.len() => {
+                            rest.strip_suffix(literal.as_str())?.len()
+                        }
+                        Some(TemplatePart::Literal(literal)) => {
+                            // values are never empty, search after the first character
+                            let start = rest.chars().next()?.len_utf8();
+                            start + rest[start..].find(literal.as_str())?
+                        }
+                        // rejected by `parse`
+                        Some(TemplatePart::Variable { .. }) => return None,
+                    };
+                    if end == 0 || end > max {
+                        return None;
+                    }
+                    variables.insert(name.clone(), percent_decode(&rest[..end]));
+                    rest = &rest[end..];
+                }
+            }
+        }
+        rest.is_empty().then_some(variables)
+    }
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| value.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Return the page of `items` starting at `cursor` and the cursor of the next page
+/// cursors are opaque to clients, here they are the offset of the first item
+pub(crate) fn paginate<I: Iterator>(
+    items: I,
+    cursor: Option<&str>,
+    page_size: Option<usize>,
+) -> Result<(Vec<I::Item>, Option<String>)> {
+    let offset = match cursor {
+        Some(cursor) => cursor
+            .parse::<usize>()
+            .map_err(|_| McpError::invalid_params(format!("Invalid cursor: {cursor}")))?,
+        None => 0,
+    };
+    let mut items = items.skip(offset);
+    let Some(page_size) = page_size else {
+        return Ok((items.collect(), None));
+    };
+    let page: Vec<_> = items.by_ref().take(page_size).collect();
+    let next_cursor = items.next().map(|_| (offset + page.len()).to_string());
+    Ok((page, next_cursor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paginate() {
+        let (page, cursor) = paginate(0..5, None, Some(2)).unwrap();
+        assert_eq!((page, cursor.as_deref()), (vec![0, 1], Some("2")));
+        let (page, cursor) = paginate(0..5, Some("4"), Some(2)).unwrap();
+        assert_eq!((page, cursor), (vec![4], None));
+        let (page, cursor) = paginate(0..5, None, None).unwrap();
+        assert_eq!((page.len(), cursor), (5, None));
+        assert!(paginate(0..5, Some("nope"), Some(2)).is_err());
+    }
+
+    #[test]
+    fn test_uri_template_match() {
+        let template = UriTemplate::parse("db://{table}/{id}").unwrap();
+        let variables = template.match_uri("db://users/42").unwrap();
+        assert_eq!(variables["table"], "users");
+        assert_eq!(variables["id"], "42");
+        assert!(template.match_uri("db://users/42/extra").is_none());
+        assert!(template.match_uri("db://users/").is_none());
+
+        let template = UriTemplate::parse("file:///{+path}").unwrap();
+        let variables = template.match_uri("file:///docs/read%20me.md").unwrap();
+        assert_eq!(variables["path"], "docs/read me.md");
+
+        let template = UriTemplate::parse("repo://{owner}/{+path}/blob/{+file}.md").unwrap();
+        let variables = template
+            .match_uri("repo://me/src/a/blob/docs/blob/readme.md")
+            .unwrap();
+        assert_eq!(variables["owner"], "me");
+        assert_eq!(variables["path"], "src/a");
+        assert_eq!(variables["file"], "docs/blob/readme");
+
+        assert!(UriTemplate::parse("db://{table").is_err());
+        assert!(UriTemplate::parse("db://{a}{b}").is_err());
+    }
+
+    #[test]
+    fn test_long_uri_without_match() {
+        let template = UriTemplate::parse("file:///{+a}/x/{+b}/y/{+c}/z/{+d}.end").unwrap();
+        let uri = format!("file:///{}", "x/y/z/".repeat(20_000));
+        assert!(template.match_uri(&uri).is_none());
+    }
+}
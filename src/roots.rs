@@ -0,0 +1,18 @@
+//! Roots are the directories and files a client lets the server work in
+//! the server asks for them with `roots/list`
+use crate::error::{McpError, Result};
+use crate::protocol::{Peer, RequestOptions, check_client_capability};
+use crate::types::{ListRootsResult, Root};
+
+/// Send `roots/list` to `peer` and return its roots
+/// fails if the client did not advertise the roots capability
+pub(crate) async fn list_roots(peer: &dyn Peer) -> Result<Vec<Root>> {
+    check_client_capability(peer, "roots", |capabilities| capabilities.roots.is_some())?;
+    let result = peer
+        .request("roots/list", None, RequestOptions::default())
+        .await?
+        .into_result()?;
+    let result: ListRootsResult = serde_json::from_value(result)
+        .map_err(|e| McpError::Protocol(format!("Invalid roots/list response: {e}")))?;
+    Ok(result.roots)
+}
@@ -0,0 +1,39 @@
+//! Sampling lets a server ask the client to run an LLM with `sampling/createMessage`
+//! the client decides which model to use and may ask the user for approval
+use crate::context::RequestContext;
+use crate::error::{McpError, Result};
+use crate::protocol::{Peer, RequestOptions, check_client_capability};
+use crate::types::{CreateMessageRequest, CreateMessageResult};
+use async_trait::async_trait;
+
+/// Fulfils `sampling/createMessage` requests on the client, e.g. by calling a model backend
+#[async_trait]
+pub trait SamplingHandler: Send + Sync + 'static {
+    async fn create_message(
+        &self,
+        request: CreateMessageRequest,
+        ctx: RequestContext,
+    ) -> Result<CreateMessageResult>;
+}
+
+/// Send `sampling/createMessage` to `peer` and wait for the sampled message
+/// fails if the client did not advertise the sampling capability
+pub(crate) async fn create_message(
+    peer: &dyn Peer,
+    request: CreateMessageRequest,
+    options: RequestOptions,
+) -> Result<CreateMessageResult> {
+    check_client_capability(peer, "sampling", |capabilities| {
+        capabilities.sampling.is_some()
+    })?;
+    let result = peer
+        .request(
+            "sampling/createMessage",
+            Some(serde_json::to_value(request)?),
+            options,
+        )
+        .await?
+        .into_result()?;
+    serde_json::from_value(result)
+        .map_err(|e| McpError::Protocol(format!("Invalid sampling/createMessage response: {e}")))
+}
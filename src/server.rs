@@ -1,32 +1,51 @@
-use std::sync::{Arc, RwLock};
+use std::{
+    collections::HashSet,
+    sync::{Arc, RwLock},
+};
 
 use crate::{
+    context::{NotificationContext, RequestContext},
+    elicitation,
+    logging::{Logger, McpLoggingLayer},
+    prompts::Prompts,
+    resources::Resources,
+    roots, sampling,
     tools::Tools,
-    types::{CallToolRequest, ListRequest, ToolsListResponse},
+    types::{
+        CallToolRequest, CompleteRequest, CompleteResult, Completion, CompletionReference,
+        CreateMessageRequest, CreateMessageResult, ElicitRequest, ElicitResult, GetPromptRequest,
+        ListRequest, LoggingCapabilities, PromptCapabilities, ReadResourceRequest,
+        ResourceCapabilities, ResourceUpdatedNotification, Root, SetLevelRequest, SubscribeRequest,
+        ToolsListResponse,
+    },
 };
 
 use super::{
-    protocol::{Protocol, ProtocolBuilder},
+    protocol::{Protocol, ProtocolBuilder, RequestOptions, SharedClientCapabilities},
     transport::Transport,
     types::{
         ClientCapabilities, Implementation, InitializeRequest, InitializeResponse,
-        ServerCapabilities, LATEST_PROTOCOL_VERSION,
+        LATEST_PROTOCOL_VERSION, SUPPORTED_PROTOCOL_VERSIONS, ServerCapabilities,
     },
 };
-use anyhow::Result;
-use serde::{de::DeserializeOwned, Serialize};
+use crate::error::{McpError, Result};
+use serde::{Serialize, de::DeserializeOwned};
+use url::Url;
 
 #[derive(Clone)]
 pub struct ServerState {
-    client_capabilities: Option<ClientCapabilities>,
     client_info: Option<Implementation>,
     initialized: bool,
+    /// uris the client subscribed to with `resources/subscribe`
+    subscriptions: HashSet<String>,
 }
 
 #[derive(Clone)]
 pub struct Server<T: Transport> {
     protocol: Protocol<T>,
     state: Arc<RwLock<ServerState>>,
+    /// the capabilities sent in the `initialize` response
+    capabilities: Arc<ServerCapabilities>,
 }
 
 pub struct ServerBuilder<T: Transport> {
@@ -34,6 +53,8 @@ pub struct ServerBuilder<T: Transport> {
     server_info: Implementation,
     capabilities: ServerCapabilities,
     tools: Option<Tools>,
+    resources: Option<Resources>,
+    prompts: Option<Prompts>,
 }
 
 impl<T: Transport> ServerBuilder<T> {
@@ -52,6 +73,12 @@ impl<T: Transport> ServerBuilder<T> {
         self
     }
 
+    /// Set the maximum number of requests handled concurrently
+    pub fn max_concurrent_requests(mut self, max: usize) -> Self {
+        self.protocol = self.protocol.max_concurrent_requests(max);
+        self
+    }
+
     /// Register a typed request handler
     /// for higher-level api use add tool
     pub fn request_handler<Req, Resp>(
@@ -67,6 +94,49 @@ impl<T: Transport> ServerBuilder<T> {
         self
     }
 
+    /// Register a typed async request handler that also receives the [`RequestContext`]
+    pub fn context_request_handler<Req, Resp, Fut>(
+        mut self,
+        method: &str,
+        handler: impl Fn(Req, RequestContext) -> Fut + Send + Sync + 'static,
+    ) -> Self
+    where
+        Req: DeserializeOwned + Send + Sync + 'static,
+        Resp: Serialize + Send + Sync + 'static,
+        Fut: Future<Output = Result<Resp>> + Send + 'static,
+    {
+        self.protocol = self.protocol.context_request_handler(method, handler);
+        self
+    }
+
+    /// Register a typed async request handler
+    pub fn async_request_handler<Req, Resp, Fut>(
+        mut self,
+        method: &str,
+        handler: impl Fn(Req) -> Fut + Send + Sync + 'static,
+    ) -> Self
+    where
+        Req: DeserializeOwned + Send + Sync + 'static,
+        Resp: Serialize + Send + Sync + 'static,
+        Fut: Future<Output = Result<Resp>> + Send + 'static,
+    {
+        self.protocol = self.protocol.async_request_handler(method, handler);
+        self
+    }
+
+    /// Register an async request handler working on the raw json params and result
+    pub fn raw_request_handler<Fut>(
+        mut self,
+        method: &str,
+        handler: impl Fn(Option<serde_json::Value>) -> Fut + Send + Sync + 'static,
+    ) -> Self
+    where
+        Fut: Future<Output = Result<serde_json::Value>> + Send + 'static,
+    {
+        self.protocol = self.protocol.raw_request_handler(method, handler);
+        self
+    }
+
     pub fn notification_handler<N>(
         mut self,
         method: &str,
@@ -79,11 +149,83 @@ impl<T: Transport> ServerBuilder<T> {
         self
     }
 
+    /// Register a typed async notification handler
+    pub fn async_notification_handler<N, Fut>(
+        mut self,
+        method: &str,
+        handler: impl Fn(N) -> Fut + Send + Sync + 'static,
+    ) -> Self
+    where
+        N: DeserializeOwned + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.protocol = self.protocol.async_notification_handler(method, handler);
+        self
+    }
+
+    /// Register a typed async notification handler that also receives the [`NotificationContext`]
+    pub fn context_notification_handler<N, Fut>(
+        mut self,
+        method: &str,
+        handler: impl Fn(N, NotificationContext) -> Fut + Send + Sync + 'static,
+    ) -> Self
+    where
+        N: DeserializeOwned + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.protocol = self.protocol.context_notification_handler(method, handler);
+        self
+    }
+
+    /// Register an async notification handler working on the raw json params
+    pub fn raw_notification_handler<Fut>(
+        mut self,
+        method: &str,
+        handler: impl Fn(Option<serde_json::Value>) -> Fut + Send + Sync + 'static,
+    ) -> Self
+    where
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.protocol = self.protocol.raw_notification_handler(method, handler);
+        self
+    }
+
     pub fn tools(mut self, tools: Tools) -> Self {
         self.tools = Some(tools);
         self
     }
 
+    /// Serve `resources/list`, `resources/templates/list` and `resources/read` from `resources`
+    /// the resources capability is advertised automatically, without `listChanged`
+    pub fn resources(mut self, resources: Resources) -> Self {
+        self.resources = Some(resources);
+        self
+    }
+
+    /// Call `handler` when the client sends `notifications/roots/list_changed`
+    /// use [`NotificationContext::list_roots`] to fetch the new roots
+    pub fn on_roots_list_changed<Fut>(
+        mut self,
+        handler: impl Fn(NotificationContext) -> Fut + Send + Sync + 'static,
+    ) -> Self
+    where
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.protocol = self
+            .protocol
+            .context_notification_handler("notifications/roots/list_changed", move |_: (), ctx| {
+                handler(ctx)
+            });
+        self
+    }
+
+    /// Serve `prompts/list` and `prompts/get` from `prompts`
+    /// the prompts capability is advertised automatically
+    pub fn prompts(mut self, prompts: Prompts) -> Self {
+        self.prompts = Some(prompts);
+        self
+    }
+
     pub fn build(self) -> Server<T> {
         Server::new(self)
     }
@@ -99,27 +241,67 @@ impl<T: Transport> Server<T> {
             },
             capabilities: Default::default(),
             tools: None,
+            resources: None,
+            prompts: None,
         }
     }
 
-    fn new(builder: ServerBuilder<T>) -> Self {
+    fn new(mut builder: ServerBuilder<T>) -> Self {
         let state = Arc::new(RwLock::new(ServerState {
-            client_capabilities: None,
             client_info: None,
             initialized: false,
+            subscriptions: HashSet::new(),
         }));
 
+        if builder.resources.is_some() {
+            let capabilities = builder
+                .capabilities
+                .resources
+                .get_or_insert_with(ResourceCapabilities::default);
+            // the resources are fixed once built, `list_changed` is only advertised
+            // when set through `capabilities` by servers that serve a changing list
+            capabilities.subscribe.get_or_insert(true);
+        }
+        if builder.prompts.is_some() {
+            builder
+                .capabilities
+                .prompts
+                .get_or_insert_with(PromptCapabilities::default);
+        }
+        if builder.prompts.is_some() || builder.resources.is_some() {
+            builder
+                .capabilities
+                .completions
+                .get_or_insert_with(|| serde_json::json!({}));
+        }
+
+        builder
+            .capabilities
+            .logging
+            .get_or_insert_with(LoggingCapabilities::default);
+
         // Initialize protocol with handlers
+        let log_level = builder.protocol.log_level();
+        let client_capabilities = builder.protocol.client_capabilities();
         let mut protocol = builder
             .protocol
             .request_handler(
                 "initialize",
-                Self::handle_init(state.clone(), builder.server_info, builder.capabilities),
+                Self::handle_init(
+                    state.clone(),
+                    client_capabilities,
+                    builder.server_info,
+                    builder.capabilities.clone(),
+                ),
             )
             .notification_handler(
                 "notifications/initialized",
                 Self::handle_initialized(state.clone()),
-            );
+            )
+            .request_handler("logging/setLevel", move |req: SetLevelRequest| {
+                log_level.set(req.level);
+                Ok(serde_json::json!({}))
+            });
         if let Some(tools) = builder.tools {
             // Add tools handlers if not already present
             let tools = Arc::new(tools);
@@ -132,33 +314,88 @@ impl<T: Transport> Server<T> {
                         meta: None,
                     })
                 })
-                .request_handler("tools/call", move |req: CallToolRequest| {
-                    let response = tools_clone.call_tool(req);
-                    Ok(response)
+                .context_request_handler("tools/call", move |req: CallToolRequest, ctx| {
+                    let tools = tools_clone.clone();
+                    async move { Ok(tools.call_tool(req, ctx).await) }
                 });
         }
+        let resources = builder.resources.map(Arc::new);
+        let prompts = builder.prompts.map(Arc::new);
+        if let Some(resources) = resources.clone() {
+            let list = resources.clone();
+            let templates = resources.clone();
+            protocol = protocol
+                .request_handler("resources/list", move |req: ListRequest| {
+                    list.list_resources(req)
+                })
+                .request_handler("resources/templates/list", move |req: ListRequest| {
+                    templates.list_resource_templates(req)
+                })
+                .context_request_handler("resources/read", move |req: ReadResourceRequest, ctx| {
+                    let resources = resources.clone();
+                    async move { resources.read_resource(req, ctx).await }
+                })
+                .request_handler(
+                    "resources/subscribe",
+                    Self::handle_subscribe(state.clone(), true),
+                )
+                .request_handler(
+                    "resources/unsubscribe",
+                    Self::handle_subscribe(state.clone(), false),
+                );
+        }
+        if let Some(prompts) = prompts.clone() {
+            let list = prompts.clone();
+            protocol = protocol
+                .request_handler("prompts/list", move |req: ListRequest| {
+                    list.list_prompts(req)
+                })
+                .context_request_handler("prompts/get", move |req: GetPromptRequest, ctx| {
+                    let prompts = prompts.clone();
+                    async move { prompts.get_prompt(req, ctx).await }
+                });
+        }
+        if prompts.is_some() || resources.is_some() {
+            protocol = protocol.context_request_handler(
+                "completion/complete",
+                move |req: CompleteRequest, ctx| {
+                    let prompts = prompts.clone();
+                    let resources = resources.clone();
+                    async move { Self::handle_complete(prompts, resources, req, ctx).await }
+                },
+            );
+        }
 
         Server {
             protocol: protocol.build(),
             state,
+            capabilities: Arc::new(builder.capabilities),
         }
     }
 
     // Helper function for initialize handler
     fn handle_init(
         state: Arc<RwLock<ServerState>>,
+        client_capabilities: SharedClientCapabilities,
         server_info: Implementation,
         capabilities: ServerCapabilities,
     ) -> impl Fn(InitializeRequest) -> Result<InitializeResponse> {
         move |req| {
             let mut state = state
                 .write()
-                .map_err(|_| anyhow::anyhow!("Lock poisoned"))?;
-            state.client_capabilities = Some(req.capabilities);
+                .map_err(|_| McpError::internal("Lock poisoned"))?;
+            client_capabilities.set(req.capabilities);
             state.client_info = Some(req.client_info);
 
+            // answer with the requested version if supported, the client decides if it can use ours
+            let protocol_version =
+                if SUPPORTED_PROTOCOL_VERSIONS.contains(&req.protocol_version.as_str()) {
+                    req.protocol_version
+                } else {
+                    LATEST_PROTOCOL_VERSION.to_string()
+                };
             Ok(InitializeResponse {
-                protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
+                protocol_version,
                 capabilities: capabilities.clone(),
                 server_info: server_info.clone(),
             })
@@ -170,14 +407,140 @@ impl<T: Transport> Server<T> {
         move |_| {
             let mut state = state
                 .write()
-                .map_err(|_| anyhow::anyhow!("Lock poisoned"))?;
+                .map_err(|_| McpError::internal("Lock poisoned"))?;
             state.initialized = true;
             Ok(())
         }
     }
 
+    // Helper function for completion handler
+    async fn handle_complete(
+        prompts: Option<Arc<Prompts>>,
+        resources: Option<Arc<Resources>>,
+        req: CompleteRequest,
+        ctx: RequestContext,
+    ) -> Result<CompleteResult> {
+        let completion = match (req.reference, prompts, resources) {
+            (CompletionReference::Prompt { name }, Some(prompts), _) => {
+                prompts.complete(&name, req.argument, ctx).await?
+            }
+            (CompletionReference::Resource { uri }, _, Some(resources)) => {
+                resources.complete(&uri, req.argument, ctx).await?
+            }
+            (reference, _, _) => {
+                return Err(McpError::invalid_params(format!(
+                    "Completion not supported for {reference:?}"
+                )));
+            }
+        };
+        // enforce the limit on values for providers that did not use `Completion::from_values`
+        let completion = if completion.values.len() > Completion::MAX_VALUES {
+            Completion::from_values(completion.values)
+        } else {
+            completion
+        };
+        Ok(CompleteResult {
+            completion,
+            meta: None,
+        })
+    }
+
+    // Helper function for subscribe and unsubscribe handlers
+    fn handle_subscribe(
+        state: Arc<RwLock<ServerState>>,
+        subscribe: bool,
+    ) -> impl Fn(SubscribeRequest) -> Result<serde_json::Value> {
+        move |req| {
+            let mut state = state
+                .write()
+                .map_err(|_| McpError::internal("Lock poisoned"))?;
+            if subscribe {
+                state.subscriptions.insert(req.uri.to_string());
+            } else {
+                state.subscriptions.remove(req.uri.as_str());
+            }
+            Ok(serde_json::json!({}))
+        }
+    }
+
+    pub fn is_subscribed(&self, uri: &Url) -> bool {
+        self.state
+            .read()
+            .map(|state| state.subscriptions.contains(uri.as_str()))
+            .unwrap_or(false)
+    }
+
+    /// Send `notifications/resources/updated` for `uri`
+    /// nothing is sent unless the client subscribed to it
+    pub async fn notify_resource_updated(&self, uri: &Url) -> Result<()> {
+        if !self.is_subscribed(uri) {
+            return Ok(());
+        }
+        let notification = ResourceUpdatedNotification { uri: uri.clone() };
+        self.protocol
+            .notify(
+                "notifications/resources/updated",
+                Some(serde_json::to_value(notification)?),
+            )
+            .await
+    }
+
+    /// Send `notifications/resources/list_changed` after resources were added or removed
+    /// only for servers advertising `listChanged` in their resources capability
+    pub async fn notify_resource_list_changed(&self) -> Result<()> {
+        let list_changed = self
+            .capabilities
+            .resources
+            .as_ref()
+            .and_then(|resources| resources.list_changed)
+            .unwrap_or(false);
+        if !list_changed {
+            return Ok(());
+        }
+        self.protocol
+            .notify("notifications/resources/list_changed", None)
+            .await
+    }
+
+    /// Logger sending `notifications/message` to the client
+    pub fn logger(&self) -> Logger {
+        Logger::new(Arc::new(self.protocol.clone()))
+    }
+
+    /// A `tracing` layer forwarding events to the client, honouring `logging/setLevel`
+    /// must be called from within a tokio runtime
+    pub fn logging_layer(&self) -> McpLoggingLayer {
+        McpLoggingLayer::new(self.logger())
+    }
+
+    /// Ask the client to sample an LLM
+    /// fails if the client did not advertise the sampling capability
+    pub async fn create_message(
+        &self,
+        request: CreateMessageRequest,
+        options: RequestOptions,
+    ) -> Result<CreateMessageResult> {
+        sampling::create_message(&self.protocol, request, options).await
+    }
+
+    /// Ask the user for structured input through the client
+    /// fails if the client did not advertise the elicitation capability
+    pub async fn elicit(
+        &self,
+        request: ElicitRequest,
+        options: RequestOptions,
+    ) -> Result<ElicitResult> {
+        elicitation::elicit(&self.protocol, request, options).await
+    }
+
+    /// Ask the client for its roots
+    /// fails if the client did not advertise the roots capability
+    pub async fn list_roots(&self) -> Result<Vec<Root>> {
+        roots::list_roots(&self.protocol).await
+    }
+
     pub fn get_client_capabilities(&self) -> Option<ClientCapabilities> {
-        self.state.read().ok()?.client_capabilities.clone()
+        self.protocol.client_capabilities().get()
     }
 
     pub fn get_client_info(&self) -> Option<Implementation> {
@@ -1,12 +1,21 @@
+use crate::context::RequestContext;
 use crate::types::{CallToolRequest, CallToolResponse, ToolDefinition, ToolResponseContent};
 use anyhow::Result;
+use async_trait::async_trait;
 use std::{collections::HashMap, sync::Arc};
 
+#[async_trait]
 pub trait Tool: Send + Sync + 'static {
     fn name(&self) -> String;
     fn description(&self) -> String;
     fn input_schema(&self) -> serde_json::Value;
-    fn call(&self, input: Option<serde_json::Value>) -> Result<CallToolResponse>;
+    /// Run the tool, `ctx` carries the request id, `_meta`, cancellation,
+    /// progress reporting and logging back to the client
+    async fn call(
+        &self,
+        input: Option<serde_json::Value>,
+        ctx: RequestContext,
+    ) -> Result<CallToolResponse>;
     fn as_definition(&self) -> ToolDefinition {
         ToolDefinition {
             name: self.name(),
@@ -33,9 +42,12 @@ impl Tools {
             .collect()
     }
 
-    pub fn call_tool(&self, request: CallToolRequest) -> CallToolResponse {
-        let tool = self.tools.get(&request.name);
-        if tool.is_none() {
+    pub async fn call_tool(
+        &self,
+        request: CallToolRequest,
+        ctx: RequestContext,
+    ) -> CallToolResponse {
+        let Some(tool) = self.tools.get(&request.name) else {
             return CallToolResponse {
                 content: vec![ToolResponseContent::Text {
                     text: format!("Tool {} not found", request.name),
@@ -43,22 +55,16 @@ impl Tools {
                 is_error: Some(true),
                 meta: None,
             };
-        }
-        let arguments = request.arguments;
-        let result = tool.unwrap().call(arguments);
-        if result.is_err() {
-            return CallToolResponse {
+        };
+        match tool.call(request.arguments, ctx).await {
+            Ok(response) => response,
+            Err(e) => CallToolResponse {
                 content: vec![ToolResponseContent::Text {
-                    text: format!(
-                        "Error calling tool {}: {}",
-                        &request.name,
-                        result.err().unwrap()
-                    ),
+                    text: format!("Error calling tool {}: {}", &request.name, e),
                 }],
                 is_error: Some(true),
                 meta: None,
-            };
+            },
         }
-        result.unwrap()
     }
 }
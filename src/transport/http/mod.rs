@@ -0,0 +1,158 @@
+//! HTTP based transports
+//! Streamable HTTP from the 2025-03-26 specification and HTTP+SSE from 2024-11-05
+mod sse;
+mod sse_client;
+mod sse_server;
+mod streamable_client;
+mod streamable_server;
+pub use sse_client::*;
+pub use sse_server::*;
+pub use streamable_client::*;
+pub use streamable_server::*;
+
+use super::{
+    InvalidMessage, JsonRpcError, JsonRpcMessage, JsonRpcResponse, Message, RequestId,
+    parse_batch_element, parse_message,
+};
+use crate::types::ErrorCode;
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+
+/// Header carrying the session id assigned by the server on initialize
+pub const SESSION_ID_HEADER: &str = "mcp-session-id";
+/// Header used by a client to resume an SSE stream after the last event it received
+pub const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+/// The messages of a batch, or the message itself
+/// invalid batch elements are returned as the error response they get
+fn parse_messages(message: &Message) -> Vec<Result<Message, Box<JsonRpcResponse>>> {
+    match message {
+        JsonRpcMessage::Batch(elements) => elements
+            .iter()
+            .map(|element| parse_batch_element(element.clone()))
+            .collect(),
+        message => vec![Ok(message.clone())],
+    }
+}
+
+/// The valid messages of a batch, or the message itself
+fn messages(message: &Message) -> Vec<Message> {
+    parse_messages(message)
+        .into_iter()
+        .filter_map(Result::ok)
+        .collect()
+}
+
+/// The ids of the responses expected for `message`
+/// including the error responses to invalid batch elements
+fn request_ids(message: &Message) -> Vec<RequestId> {
+    let mut ids = Vec::new();
+    for message in parse_messages(message) {
+        let id = match message {
+            Ok(JsonRpcMessage::Request(request)) => request.id,
+            Err(response) => response.id,
+            Ok(_) => continue,
+        };
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+fn response_ids(message: &Message) -> Vec<RequestId> {
+    messages(message)
+        .iter()
+        .filter_map(|message| match message {
+            JsonRpcMessage::Response(response) => Some(response.id.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// A request rejected before it reached a session
+struct HttpError(StatusCode, &'static str);
+
+impl IntoResponse for HttpError {
+    fn into_response(self) -> Response {
+        error_response(self.0, ErrorCode::InvalidRequest, self.1, None)
+    }
+}
+
+/// A JSON-RPC error without request id as the body of an HTTP error
+fn error_response(
+    status: StatusCode,
+    code: ErrorCode,
+    message: &str,
+    data: Option<serde_json::Value>,
+) -> Response {
+    let response = JsonRpcResponse {
+        id: RequestId::Null,
+        error: Some(JsonRpcError {
+            code: code as i32,
+            message: message.to_string(),
+            data,
+        }),
+        ..Default::default()
+    };
+    (status, axum::Json(response)).into_response()
+}
+
+/// Parse a POSTed message, see [`parse_message`]
+fn parse_body(body: &[u8]) -> anyhow::Result<Message> {
+    parse_message(std::str::from_utf8(body)?)
+}
+
+/// Answer a body [`parse_body`] rejected with 400 and a JSON-RPC error
+/// which keeps the id of an invalid message when it can be read
+fn parse_error(error: anyhow::Error) -> Response {
+    match error.downcast::<InvalidMessage>() {
+        Ok(InvalidMessage(response)) => {
+            (StatusCode::BAD_REQUEST, axum::Json(response)).into_response()
+        }
+        Err(e) => error_response(
+            StatusCode::BAD_REQUEST,
+            ErrorCode::ParseError,
+            "Parse error",
+            Some(e.to_string().into()),
+        ),
+    }
+}
+
+/// The browser origins a server accepts requests from, guards against DNS rebinding
+#[derive(Debug, Clone, Default)]
+enum AllowedOrigins {
+    /// pages served from `localhost`, `127.0.0.1` or `[::1]` on any port
+    #[default]
+    Localhost,
+    List(Vec<String>),
+    Any,
+}
+
+impl AllowedOrigins {
+    /// Reject requests from browsers on other origins
+    /// requests without an `Origin` header are not sent by browsers and allowed
+    fn check(&self, headers: &HeaderMap) -> Result<(), HttpError> {
+        let Some(origin) = headers.get(header::ORIGIN) else {
+            return Ok(());
+        };
+        let allowed = match self {
+            AllowedOrigins::Localhost => origin
+                .to_str()
+                .ok()
+                .and_then(|origin| url::Url::parse(origin).ok())
+                .is_some_and(|origin| {
+                    matches!(origin.scheme(), "http" | "https")
+                        && matches!(origin.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"))
+                }),
+            AllowedOrigins::List(allowed) => {
+                allowed.iter().any(|allowed| origin == allowed.as_str())
+            }
+            AllowedOrigins::Any => true,
+        };
+        if allowed {
+            return Ok(());
+        }
+        Err(HttpError(StatusCode::FORBIDDEN, "Origin not allowed"))
+    }
+}
@@ -0,0 +1,96 @@
+/// An event of a `text/event-stream` body
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct SseEvent {
+    pub id: Option<String>,
+    pub event: Option<String>,
+    pub data: String,
+}
+
+/// Incremental parser of `text/event-stream` bodies
+/// chunks may split lines and utf-8 characters anywhere
+#[derive(Default)]
+pub(crate) struct SseParser {
+    buffer: Vec<u8>,
+    event: SseEvent,
+    has_data: bool,
+}
+
+impl SseParser {
+    /// Parse a chunk of the body, returning the events it completed
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buffer.extend_from_slice(chunk);
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if let Some(event) = self.line(line) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    fn line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            let mut event = std::mem::take(&mut self.event);
+            // events without data are not dispatched
+            if !std::mem::take(&mut self.has_data) {
+                return None;
+            }
+            if event.data.ends_with('\n') {
+                event.data.pop();
+            }
+            return Some(event);
+        }
+        // lines starting with a colon are comments, e.g. keep alive
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "data" => {
+                self.event.data.push_str(value);
+                self.event.data.push('\n');
+                self.has_data = true;
+            }
+            "id" => self.event.id = Some(value.to_string()),
+            "event" => self.event.event = Some(value.to_string()),
+            _ => {}
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sse_parser() {
+        let mut parser = SseParser::default();
+        assert!(
+            parser
+                .feed(b": keep alive\n\nid: 1\ndata: {\"a\"")
+                .is_empty()
+        );
+        let events =
+            parser.feed(b":1}\r\n\r\nevent: endpoint\ndata: /messages\ndata: x\n\nid: 2\n\n");
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    id: Some("1".to_string()),
+                    event: None,
+                    data: "{\"a\":1}".to_string(),
+                },
+                SseEvent {
+                    id: None,
+                    event: Some("endpoint".to_string()),
+                    data: "/messages\nx".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,296 @@
+use super::sse::{SseEvent, SseParser};
+use crate::transport::{Message, Transport};
+use anyhow::Result;
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use reqwest::Url;
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use std::sync::Arc;
+use tokio::sync::{Mutex, OnceCell, mpsc};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+/// Client transport for servers using the HTTP+SSE transport of the 2024-11-05 specification
+///
+/// Connecting opens the event stream and waits for the `endpoint` event,
+/// messages are then POSTed to that endpoint and responses arrive on the stream.
+/// The transport connects on `open`, or on the first message sent.
+#[derive(Clone)]
+pub struct SseClientTransport {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    client: reqwest::Client,
+    url: Url,
+    endpoint: OnceCell<Url>,
+    incoming_tx: std::sync::Mutex<Option<mpsc::UnboundedSender<Message>>>,
+    incoming_rx: Mutex<mpsc::UnboundedReceiver<Message>>,
+    closed: CancellationToken,
+}
+
+impl SseClientTransport {
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Self::with_client(reqwest::Client::new(), Url::parse(url)?))
+    }
+
+    /// Use a configured `reqwest` client, e.g. with TLS or default headers
+    pub fn with_client(client: reqwest::Client, url: Url) -> Self {
+        let (incoming_tx, incoming_rx) = mpsc::unbounded_channel();
+        Self {
+            inner: Arc::new(Inner {
+                client,
+                url,
+                endpoint: OnceCell::new(),
+                incoming_tx: std::sync::Mutex::new(Some(incoming_tx)),
+                incoming_rx: Mutex::new(incoming_rx),
+                closed: CancellationToken::new(),
+            }),
+        }
+    }
+
+    async fn endpoint(&self) -> Result<&Url> {
+        self.inner
+            .endpoint
+            .get_or_try_init(|| self.inner.clone().connect())
+            .await
+    }
+}
+
+impl Inner {
+    /// Open the event stream and read it until the `endpoint` event
+    /// the rest of the stream is read by a spawned task
+    async fn connect(self: Arc<Self>) -> Result<Url> {
+        if self.incoming_tx.lock().unwrap().is_none() {
+            return Err(anyhow::anyhow!("Transport closed"));
+        }
+        let response = self
+            .client
+            .get(self.url.clone())
+            .header(ACCEPT, "text/event-stream")
+            .send()
+            .await?
+            .error_for_status()?;
+        let mut body = response.bytes_stream();
+        let mut parser = SseParser::default();
+        // events read in the same chunk as the endpoint are forwarded once connected
+        let (endpoint, pending) = loop {
+            let Some(chunk) = body.next().await else {
+                return Err(anyhow::anyhow!(
+                    "Event stream closed before the endpoint event"
+                ));
+            };
+            let mut events = parser.feed(&chunk?);
+            if let Some(index) = events
+                .iter()
+                .position(|event| event.event.as_deref() == Some("endpoint"))
+            {
+                let pending = events.split_off(index + 1);
+                break (self.url.join(&events[index].data)?, pending);
+            }
+        };
+        if endpoint.origin() != self.url.origin() {
+            return Err(anyhow::anyhow!(
+                "Endpoint {endpoint} is not on the origin of {}",
+                self.url
+            ));
+        }
+        // taken only once connected so that a failed attempt can be retried
+        let Some(incoming) = self.incoming_tx.lock().unwrap().take() else {
+            return Err(anyhow::anyhow!("Transport closed"));
+        };
+        debug!("Posting messages to {endpoint}");
+        for event in pending {
+            forward(&incoming, event);
+        }
+
+        tokio::spawn(async move {
+            loop {
+                let chunk = tokio::select! {
+                    chunk = body.next() => chunk,
+                    _ = self.closed.cancelled() => break,
+                };
+                let chunk = match chunk {
+                    Some(Ok(chunk)) => chunk,
+                    Some(Err(e)) => {
+                        warn!("Event stream failed: {e}");
+                        break;
+                    }
+                    None => break,
+                };
+                for event in parser.feed(&chunk) {
+                    forward(&incoming, event);
+                }
+            }
+            debug!("Event stream closed");
+        });
+        Ok(endpoint)
+    }
+}
+
+/// Forward the message of a `message` event, other events are ignored
+fn forward(incoming: &mpsc::UnboundedSender<Message>, event: SseEvent) {
+    if !matches!(event.event.as_deref(), None | Some("message")) {
+        return;
+    }
+    match serde_json::from_str(&event.data) {
+        Ok(message) => {
+            let _ = incoming.send(message);
+        }
+        Err(e) => warn!("Invalid message on event stream: {e}"),
+    }
+}
+
+#[async_trait]
+impl Transport for SseClientTransport {
+    async fn send(&self, message: &Message) -> Result<()> {
+        let endpoint = self.endpoint().await?;
+        if self.inner.closed.is_cancelled() {
+            return Err(anyhow::anyhow!("Transport closed"));
+        }
+        let response = self
+            .inner
+            .client
+            .post(endpoint.clone())
+            .header(CONTENT_TYPE, "application/json")
+            .body(serde_json::to_vec(message)?)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!("POST {endpoint} failed ({status}): {body}"));
+        }
+        Ok(())
+    }
+
+    /// Returns `None` once the event stream is closed
+    async fn receive(&self) -> Result<Option<Message>> {
+        Ok(self.inner.incoming_rx.lock().await.recv().await)
+    }
+
+    async fn open(&self) -> Result<()> {
+        self.endpoint().await?;
+        Ok(())
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.inner.closed.cancel();
+        // never connected, nothing will be received
+        self.inner.incoming_tx.lock().unwrap().take();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Client;
+    use crate::server::Server;
+    use crate::transport::SseServer;
+    use crate::types::{Implementation, LoggingLevel, Root};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Answer each connection with the next of `responses` in a single write
+    /// connections are kept open until the server task ends
+    async fn serve_raw(responses: Vec<&'static str>) -> Result<std::net::SocketAddr> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            for response in responses {
+                let Ok((mut stream, _)) = listener.accept().await else {
+                    return;
+                };
+                let mut request = Vec::new();
+                while !request.ends_with(b"\r\n\r\n") {
+                    let mut buf = [0; 1024];
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let _ = stream.write_all(response.as_bytes()).await;
+                connections.push(stream);
+            }
+            std::future::pending::<()>().await;
+        });
+        Ok(addr)
+    }
+
+    #[tokio::test]
+    async fn test_client_over_sse() -> Result<()> {
+        let sse = SseServer::bind("127.0.0.1:0", Default::default()).await?;
+        let transport = SseClientTransport::new(&format!("http://{}/sse", sse.local_addr()))?;
+        transport.open().await?;
+        let session = sse.accept().await.unwrap();
+        let server = Server::builder(session.clone()).build();
+        tokio::spawn({
+            let server = server.clone();
+            async move { server.listen().await }
+        });
+
+        let (log_tx, mut log_rx) = mpsc::unbounded_channel();
+        let root = Root {
+            uri: Url::parse("file:///workspace")?,
+            name: None,
+        };
+        let client = Client::builder(transport.clone())
+            .roots(vec![root.clone()])
+            .on_log_message(move |message| {
+                let _ = log_tx.send(message.data);
+            })
+            .build();
+        tokio::spawn({
+            let client = client.clone();
+            async move { client.start().await }
+        });
+        client.initialize(Implementation::default()).await?;
+        client.ping().await?;
+        server
+            .logger()
+            .log(LoggingLevel::Info, "hello".into())
+            .await?;
+        assert_eq!(log_rx.recv().await.unwrap(), "hello");
+        assert_eq!(server.list_roots().await?, vec![root]);
+
+        // the server ending the session closes the client
+        session.close().await?;
+        assert!(transport.receive().await?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_after_failed_open() -> Result<()> {
+        let addr = serve_raw(vec![
+            "HTTP/1.1 503 Service Unavailable\r\nconnection: close\r\ncontent-length: 0\r\n\r\n",
+            "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\r\nevent: endpoint\ndata: /messages\n\n",
+        ])
+        .await?;
+        let transport = SseClientTransport::new(&format!("http://{addr}/sse"))?;
+        assert!(transport.open().await.is_err());
+        transport.open().await?;
+        assert_eq!(
+            transport.endpoint().await?.as_str(),
+            format!("http://{addr}/messages")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_message_with_endpoint_event() -> Result<()> {
+        let addr = serve_raw(vec![concat!(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\r\n",
+            "event: endpoint\ndata: /messages\n\n",
+            "event: message\ndata: {\"jsonrpc\":\"2.0\",\"method\":\"notifications/initialized\"}\n\n",
+        )])
+        .await?;
+        let transport = SseClientTransport::new(&format!("http://{addr}/sse"))?;
+        transport.open().await?;
+        let Some(Message::Notification(notification)) = transport.receive().await? else {
+            panic!("expected the notification sent with the endpoint");
+        };
+        assert_eq!(notification.method, "notifications/initialized");
+        Ok(())
+    }
+}
@@ -0,0 +1,381 @@
+use super::{AllowedOrigins, HttpError, parse_body, parse_error};
+use crate::transport::{Message, Transport};
+use anyhow::Result;
+use async_trait::async_trait;
+use axum::{
+    Router,
+    body::Bytes,
+    extract::{RawQuery, State},
+    http::{HeaderMap, StatusCode},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::{get, post},
+};
+use futures_util::StreamExt;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Weak};
+use tokio::sync::{Mutex, mpsc};
+use tokio::task::AbortHandle;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tracing::{debug, warn};
+
+/// Options of [`SseServer`]
+#[derive(Debug, Clone)]
+pub struct SseServerOptions {
+    sse_path: String,
+    message_path: String,
+    allowed_origins: AllowedOrigins,
+}
+
+impl Default for SseServerOptions {
+    fn default() -> Self {
+        Self {
+            sse_path: "/sse".to_string(),
+            message_path: "/messages".to_string(),
+            allowed_origins: AllowedOrigins::default(),
+        }
+    }
+}
+
+impl SseServerOptions {
+    /// The path clients connect to for the event stream, `/sse` by default
+    pub fn sse_path(mut self, path: impl Into<String>) -> Self {
+        self.sse_path = path.into();
+        self
+    }
+
+    /// The path announced in the `endpoint` event for POSTing messages, `/messages` by default
+    pub fn message_path(mut self, path: impl Into<String>) -> Self {
+        self.message_path = path.into();
+        self
+    }
+
+    /// Reject requests whose `Origin` header is not in `origins`
+    /// by default only pages served from localhost are allowed, as for the Streamable HTTP server
+    pub fn allowed_origins(mut self, origins: Vec<String>) -> Self {
+        self.allowed_origins = AllowedOrigins::List(origins);
+        self
+    }
+
+    /// Accept requests from any `Origin`
+    pub fn allow_any_origin(mut self) -> Self {
+        self.allowed_origins = AllowedOrigins::Any;
+        self
+    }
+}
+
+/// HTTP+SSE server of the 2024-11-05 specification
+///
+/// Each GET on the SSE path opens a session whose first event announces the endpoint
+/// to POST messages to. The session ends when the client disconnects from the stream.
+pub struct SseServer {
+    sessions: Mutex<mpsc::UnboundedReceiver<SseServerTransport>>,
+    local_addr: SocketAddr,
+    task: AbortHandle,
+}
+
+impl SseServer {
+    /// Listen on `addr` and serve the SSE and message endpoints
+    pub async fn bind(
+        addr: impl tokio::net::ToSocketAddrs,
+        options: SseServerOptions,
+    ) -> Result<Self> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let (accept_tx, accept_rx) = mpsc::unbounded_channel();
+        let shared = Arc::new(Shared {
+            sessions: std::sync::Mutex::new(HashMap::new()),
+            accept: accept_tx,
+            options,
+        });
+        let router = Router::new()
+            .route(&shared.options.sse_path, get(handle_sse))
+            .route(&shared.options.message_path, post(handle_message))
+            .with_state(shared);
+        let task = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, router).await {
+                warn!("SSE server failed: {e}");
+            }
+        })
+        .abort_handle();
+        debug!("SSE server listening on {local_addr}");
+        Ok(Self {
+            sessions: Mutex::new(accept_rx),
+            local_addr,
+            task,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Wait for the next client to connect
+    pub async fn accept(&self) -> Option<SseServerTransport> {
+        self.sessions.lock().await.recv().await
+    }
+}
+
+impl Drop for SseServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct Shared {
+    sessions: std::sync::Mutex<HashMap<String, Arc<Session>>>,
+    accept: mpsc::UnboundedSender<SseServerTransport>,
+    options: SseServerOptions,
+}
+
+impl Shared {
+    fn remove_session(&self, id: &str) {
+        if let Some(session) = self.sessions.lock().unwrap().remove(id) {
+            session.close();
+        }
+    }
+}
+
+struct Session {
+    id: String,
+    incoming_tx: std::sync::Mutex<Option<mpsc::UnboundedSender<Message>>>,
+    incoming_rx: Mutex<mpsc::UnboundedReceiver<Message>>,
+    /// the event stream of the session, `None` once closed
+    outgoing: std::sync::Mutex<Option<mpsc::UnboundedSender<Message>>>,
+}
+
+impl Session {
+    fn close(&self) {
+        self.incoming_tx.lock().unwrap().take();
+        self.outgoing.lock().unwrap().take();
+    }
+}
+
+/// Ends the session once its event stream is dropped, i.e. the client disconnected
+struct StreamGuard {
+    id: String,
+    shared: Weak<Shared>,
+}
+
+impl Drop for StreamGuard {
+    fn drop(&mut self) {
+        if let Some(shared) = self.shared.upgrade() {
+            debug!("Session {} disconnected", self.id);
+            shared.remove_session(&self.id);
+        }
+    }
+}
+
+/// Transport of a single session of an [`SseServer`]
+#[derive(Clone)]
+pub struct SseServerTransport {
+    session: Arc<Session>,
+    shared: Weak<Shared>,
+}
+
+impl SseServerTransport {
+    pub fn session_id(&self) -> &str {
+        &self.session.id
+    }
+}
+
+#[async_trait]
+impl Transport for SseServerTransport {
+    async fn send(&self, message: &Message) -> Result<()> {
+        match &*self.session.outgoing.lock().unwrap() {
+            Some(outgoing) if outgoing.send(message.clone()).is_ok() => Ok(()),
+            _ => Err(anyhow::anyhow!("Session {} closed", self.session.id)),
+        }
+    }
+
+    async fn receive(&self) -> Result<Option<Message>> {
+        Ok(self.session.incoming_rx.lock().await.recv().await)
+    }
+
+    async fn open(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// End the event stream, which disconnects the client
+    async fn close(&self) -> Result<()> {
+        if let Some(shared) = self.shared.upgrade() {
+            shared.remove_session(&self.session.id);
+        }
+        self.session.close();
+        Ok(())
+    }
+}
+
+async fn handle_sse(State(shared): State<Arc<Shared>>, headers: HeaderMap) -> Response {
+    if let Err(error) = shared.options.allowed_origins.check(&headers) {
+        return error.into_response();
+    }
+    let (incoming_tx, incoming_rx) = mpsc::unbounded_channel();
+    let (outgoing_tx, outgoing_rx) = mpsc::unbounded_channel();
+    let session = Arc::new(Session {
+        id: uuid::Uuid::new_v4().to_string(),
+        incoming_tx: std::sync::Mutex::new(Some(incoming_tx)),
+        incoming_rx: Mutex::new(incoming_rx),
+        outgoing: std::sync::Mutex::new(Some(outgoing_tx)),
+    });
+    shared
+        .sessions
+        .lock()
+        .unwrap()
+        .insert(session.id.clone(), session.clone());
+    let transport = SseServerTransport {
+        session: session.clone(),
+        shared: Arc::downgrade(&shared),
+    };
+    if shared.accept.send(transport).is_err() {
+        shared.remove_session(&session.id);
+        return HttpError(StatusCode::SERVICE_UNAVAILABLE, "Server is shutting down")
+            .into_response();
+    }
+    debug!("New session {}", session.id);
+
+    let endpoint = Event::default().event("endpoint").data(format!(
+        "{}?sessionId={}",
+        shared.options.message_path, session.id
+    ));
+    let guard = StreamGuard {
+        id: session.id.clone(),
+        shared: Arc::downgrade(&shared),
+    };
+    let messages = UnboundedReceiverStream::new(outgoing_rx).map(move |message| {
+        let _guard = &guard;
+        let data = serde_json::to_string(&message).unwrap_or_default();
+        Event::default().event("message").data(data)
+    });
+    let stream = futures_util::stream::once(async { endpoint })
+        .chain(messages)
+        .map(Ok::<_, Infallible>);
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+async fn handle_message(
+    State(shared): State<Arc<Shared>>,
+    RawQuery(query): RawQuery,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Err(error) = shared.options.allowed_origins.check(&headers) {
+        return error.into_response();
+    }
+    let Some(id) = url::form_urlencoded::parse(query.unwrap_or_default().as_bytes())
+        .find(|(key, _)| key == "sessionId")
+        .map(|(_, id)| id.into_owned())
+    else {
+        return HttpError(StatusCode::BAD_REQUEST, "Missing sessionId").into_response();
+    };
+    let Some(session) = shared.sessions.lock().unwrap().get(&id).cloned() else {
+        return HttpError(StatusCode::NOT_FOUND, "Session not found").into_response();
+    };
+    let message = match parse_body(&body) {
+        Ok(message) => message,
+        Err(e) => return parse_error(e),
+    };
+    let forwarded = match &*session.incoming_tx.lock().unwrap() {
+        Some(incoming) => incoming.send(message).is_ok(),
+        None => false,
+    };
+    if !forwarded {
+        return HttpError(StatusCode::NOT_FOUND, "Session closed").into_response();
+    }
+    StatusCode::ACCEPTED.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::http::sse::SseParser;
+    use crate::transport::{JsonRpcMessage, JsonRpcNotification};
+
+    #[tokio::test]
+    async fn test_sse_session() -> Result<()> {
+        let server = SseServer::bind("127.0.0.1:0", Default::default()).await?;
+        let client = reqwest::Client::new();
+        let mut stream = client
+            .get(format!("http://{}/sse", server.local_addr()))
+            .send()
+            .await?;
+        let transport = server.accept().await.unwrap();
+
+        let mut parser = SseParser::default();
+        let mut events = Vec::new();
+        while events.is_empty() {
+            events.extend(parser.feed(&stream.chunk().await?.unwrap()));
+        }
+        assert_eq!(events[0].event.as_deref(), Some("endpoint"));
+        assert_eq!(
+            events[0].data,
+            format!("/messages?sessionId={}", transport.session_id())
+        );
+
+        let endpoint = format!("http://{}{}", server.local_addr(), events[0].data);
+        let notification = JsonRpcMessage::Notification(JsonRpcNotification {
+            method: "notifications/initialized".to_string(),
+            ..Default::default()
+        });
+        let posted = client
+            .post(&endpoint)
+            .body(serde_json::to_vec(&notification)?)
+            .send()
+            .await?;
+        assert_eq!(posted.status(), StatusCode::ACCEPTED);
+        assert_eq!(transport.receive().await?, Some(notification.clone()));
+
+        transport.send(&notification).await?;
+        let mut events = Vec::new();
+        while events.is_empty() {
+            events.extend(parser.feed(&stream.chunk().await?.unwrap()));
+        }
+        assert_eq!(events[0].event.as_deref(), Some("message"));
+        assert_eq!(
+            serde_json::from_str::<Message>(&events[0].data)?,
+            notification
+        );
+
+        // disconnecting ends the session
+        drop(stream);
+        assert!(transport.receive().await?.is_none());
+        let posted = client.post(&endpoint).body("{}").send().await?;
+        assert_eq!(posted.status(), StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_foreign_origin_rejected() -> Result<()> {
+        let server = SseServer::bind("127.0.0.1:0", Default::default()).await?;
+        let client = reqwest::Client::new();
+        let url = format!("http://{}/sse", server.local_addr());
+        let foreign = client
+            .get(&url)
+            .header(axum::http::header::ORIGIN, "http://evil.example")
+            .send()
+            .await?;
+        assert_eq!(foreign.status(), StatusCode::FORBIDDEN);
+        let local = client
+            .get(&url)
+            .header(axum::http::header::ORIGIN, "http://127.0.0.1:8080")
+            .send()
+            .await?;
+        assert_eq!(local.status(), StatusCode::OK);
+
+        let options = SseServerOptions::default().allow_any_origin();
+        let server = SseServer::bind("127.0.0.1:0", options).await?;
+        let any = client
+            .get(format!("http://{}/sse", server.local_addr()))
+            .header(axum::http::header::ORIGIN, "http://evil.example")
+            .send()
+            .await?;
+        assert_eq!(any.status(), StatusCode::OK);
+        Ok(())
+    }
+}
@@ -0,0 +1,430 @@
+use super::sse::SseParser;
+use super::{LAST_EVENT_ID_HEADER, SESSION_ID_HEADER, messages, request_ids, response_ids};
+use crate::transport::{
+    JsonRpcError, JsonRpcMessage, JsonRpcResponse, Message, RequestId, Transport,
+};
+use crate::types::ErrorCode;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use reqwest::{StatusCode, Url};
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::{Mutex, mpsc};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+/// Delay before reconnecting a dropped SSE stream
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+/// Reconnect attempts without receiving an event before giving up on a stream
+const MAX_RECONNECT_ATTEMPTS: usize = 5;
+
+/// Client transport for a Streamable HTTP MCP endpoint
+///
+/// Messages are POSTed to the endpoint, responses arrive as JSON or on an SSE stream.
+/// Once initialized a GET stream is opened for server initiated messages,
+/// dropped streams are resumed with `Last-Event-ID`.
+#[derive(Clone)]
+pub struct StreamableHttpClientTransport {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    client: reqwest::Client,
+    url: Url,
+    session_id: RwLock<Option<String>>,
+    incoming_tx: mpsc::UnboundedSender<Message>,
+    incoming_rx: Mutex<mpsc::UnboundedReceiver<Message>>,
+    closed: CancellationToken,
+}
+
+impl StreamableHttpClientTransport {
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Self::with_client(reqwest::Client::new(), Url::parse(url)?))
+    }
+
+    /// Use a configured `reqwest` client, e.g. with TLS or default headers
+    pub fn with_client(client: reqwest::Client, url: Url) -> Self {
+        let (incoming_tx, incoming_rx) = mpsc::unbounded_channel();
+        Self {
+            inner: Arc::new(Inner {
+                client,
+                url,
+                session_id: RwLock::new(None),
+                incoming_tx,
+                incoming_rx: Mutex::new(incoming_rx),
+                closed: CancellationToken::new(),
+            }),
+        }
+    }
+
+    /// The session id assigned by the server on initialize
+    pub fn session_id(&self) -> Option<String> {
+        self.inner.session_id()
+    }
+}
+
+impl Inner {
+    fn session_id(&self) -> Option<String> {
+        self.session_id.read().unwrap().clone()
+    }
+
+    fn with_session(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match self.session_id() {
+            Some(id) => request.header(SESSION_ID_HEADER, id),
+            None => request,
+        }
+    }
+
+    /// Open a GET stream, resuming after `last_event_id` if given
+    async fn get(&self, last_event_id: Option<&str>) -> Result<reqwest::Response> {
+        let mut request = self
+            .with_session(self.client.get(self.url.clone()))
+            .header(ACCEPT, "text/event-stream");
+        if let Some(id) = last_event_id {
+            request = request.header(LAST_EVENT_ID_HEADER, id);
+        }
+        Ok(request.send().await?)
+    }
+
+    /// Forward the messages of an SSE stream, reconnecting until `pending` requests are answered
+    /// the standalone GET stream (`pending` is `None`) is read until the transport is closed
+    async fn read_stream(
+        self: Arc<Self>,
+        mut response: Option<reqwest::Response>,
+        mut pending: Option<HashSet<RequestId>>,
+    ) {
+        let mut last_event_id: Option<String> = None;
+        let mut attempts = 0;
+        loop {
+            let response = match response.take() {
+                Some(response) => response,
+                None => match self.get(last_event_id.as_deref()).await {
+                    Ok(response) if response.status().is_success() => response,
+                    // the server does not offer a GET stream or one is already open
+                    Ok(response)
+                        if pending.is_none()
+                            && matches!(
+                                response.status(),
+                                StatusCode::METHOD_NOT_ALLOWED | StatusCode::CONFLICT
+                            ) =>
+                    {
+                        debug!("No GET stream: {}", response.status());
+                        return;
+                    }
+                    result => {
+                        let reason = match result {
+                            Ok(response) => response.status().to_string(),
+                            Err(e) => e.to_string(),
+                        };
+                        debug!("Reconnecting SSE stream failed: {reason}");
+                        attempts += 1;
+                        if !self.wait_reconnect(attempts).await {
+                            break;
+                        }
+                        continue;
+                    }
+                },
+            };
+            let mut parser = SseParser::default();
+            let mut body = response.bytes_stream();
+            loop {
+                let chunk = tokio::select! {
+                    chunk = body.next() => chunk,
+                    _ = self.closed.cancelled() => return,
+                };
+                let Some(Ok(chunk)) = chunk else {
+                    break;
+                };
+                for event in parser.feed(&chunk) {
+                    if event.id.is_some() {
+                        last_event_id = event.id;
+                        attempts = 0;
+                    }
+                    let message: Message = match serde_json::from_str(&event.data) {
+                        Ok(message) => message,
+                        Err(e) => {
+                            warn!("Invalid message on SSE stream: {e}");
+                            continue;
+                        }
+                    };
+                    if let Some(pending) = &mut pending {
+                        for id in response_ids(&message) {
+                            pending.remove(&id);
+                        }
+                    }
+                    let _ = self.incoming_tx.send(message);
+                }
+            }
+            if pending.as_ref().is_some_and(|pending| pending.is_empty()) {
+                return;
+            }
+            // only streams with event ids can be resumed
+            if pending.is_some() && last_event_id.is_none() {
+                break;
+            }
+            attempts += 1;
+            if !self.wait_reconnect(attempts).await {
+                break;
+            }
+        }
+        self.fail_requests(
+            pending.into_iter().flatten(),
+            "SSE stream closed before the response",
+        );
+    }
+
+    /// POST a message and forward the responses the server replies with
+    async fn post(self: Arc<Self>, message: &Message) -> Result<()> {
+        let session_id = self.session_id();
+        let response = self
+            .with_session(self.client.post(self.url.clone()))
+            .header(ACCEPT, "application/json, text/event-stream")
+            .header(CONTENT_TYPE, "application/json")
+            .body(serde_json::to_vec(message)?)
+            .send()
+            .await?;
+        if let Some(id) = response
+            .headers()
+            .get(SESSION_ID_HEADER)
+            .and_then(|id| id.to_str().ok())
+        {
+            *self.session_id.write().unwrap() = Some(id.to_string());
+        }
+        let status = response.status();
+        if status == StatusCode::NOT_FOUND
+            && let Some(id) = session_id
+        {
+            self.session_id.write().unwrap().take();
+            return Err(anyhow::anyhow!("Session {id} expired"));
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow::anyhow!(
+                "POST {} failed ({status}): {body}",
+                self.url
+            ));
+        }
+
+        if is_initialized(message) {
+            debug!("Opening GET stream");
+            tokio::spawn(self.clone().read_stream(None, None));
+        }
+        let content_type = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        if content_type.starts_with("text/event-stream") {
+            let pending = request_ids(message).into_iter().collect();
+            tokio::spawn(self.read_stream(Some(response), Some(pending)));
+        } else if content_type.starts_with("application/json") {
+            let message: Message = serde_json::from_slice(&response.bytes().await?)?;
+            let _ = self.incoming_tx.send(message);
+        }
+        Ok(())
+    }
+
+    /// Answer requests that will not get a response from the server with an error
+    fn fail_requests(&self, ids: impl IntoIterator<Item = RequestId>, message: &str) {
+        for id in ids {
+            let _ = self
+                .incoming_tx
+                .send(JsonRpcMessage::Response(JsonRpcResponse {
+                    id,
+                    error: Some(JsonRpcError {
+                        code: ErrorCode::ConnectionClosed as i32,
+                        message: message.to_string(),
+                        data: None,
+                    }),
+                    ..Default::default()
+                }));
+        }
+    }
+
+    /// Wait before the next reconnect, false once the stream should be given up
+    async fn wait_reconnect(&self, attempts: usize) -> bool {
+        if attempts > MAX_RECONNECT_ATTEMPTS {
+            warn!("SSE stream dropped, giving up after {MAX_RECONNECT_ATTEMPTS} attempts");
+            return false;
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(RECONNECT_DELAY) => true,
+            _ = self.closed.cancelled() => false,
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for StreamableHttpClientTransport {
+    /// Requests are POSTed in the background, servers may only answer once they are handled
+    /// which would otherwise keep request timeouts from firing
+    async fn send(&self, message: &Message) -> Result<()> {
+        let inner = &self.inner;
+        if inner.closed.is_cancelled() {
+            return Err(anyhow::anyhow!("Transport closed"));
+        }
+        let requests = request_ids(message);
+        if requests.is_empty() {
+            return inner.clone().post(message).await;
+        }
+        let inner = inner.clone();
+        let message = message.clone();
+        tokio::spawn(async move {
+            let result = tokio::select! {
+                result = inner.clone().post(&message) => result,
+                _ = inner.closed.cancelled() => return,
+            };
+            if let Err(e) = result {
+                debug!("POST failed: {e}");
+                inner.fail_requests(requests, &e.to_string());
+            }
+        });
+        Ok(())
+    }
+
+    async fn receive(&self) -> Result<Option<Message>> {
+        let mut incoming = self.inner.incoming_rx.lock().await;
+        tokio::select! {
+            message = incoming.recv() => Ok(message),
+            _ = self.inner.closed.cancelled() => Ok(None),
+        }
+    }
+
+    async fn open(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Stop reading streams and end the session on the server
+    async fn close(&self) -> Result<()> {
+        let inner = &self.inner;
+        inner.closed.cancel();
+        let Some(id) = inner.session_id.write().unwrap().take() else {
+            return Ok(());
+        };
+        let response = inner
+            .client
+            .delete(inner.url.clone())
+            .header(SESSION_ID_HEADER, &id)
+            .send()
+            .await?;
+        // servers may not allow clients to end sessions
+        if !response.status().is_success() && response.status() != StatusCode::METHOD_NOT_ALLOWED {
+            debug!("DELETE session {id} failed: {}", response.status());
+        }
+        Ok(())
+    }
+}
+
+fn is_initialized(message: &Message) -> bool {
+    messages(message).iter().any(|message| {
+        matches!(message, JsonRpcMessage::Notification(notification)
+            if notification.method == "notifications/initialized")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Client;
+    use crate::error::McpError;
+    use crate::protocol::RequestOptions;
+    use crate::server::Server;
+    use crate::transport::{StreamableHttpOptions, StreamableHttpServer};
+    use crate::types::{Implementation, LoggingLevel, Root};
+
+    async fn test_client_over_http(options: StreamableHttpOptions) -> Result<()> {
+        let http = StreamableHttpServer::bind("127.0.0.1:0", options).await?;
+        let transport =
+            StreamableHttpClientTransport::new(&format!("http://{}/mcp", http.local_addr()))?;
+        let (log_tx, mut log_rx) = mpsc::unbounded_channel();
+        let root = Root {
+            uri: Url::parse("file:///workspace")?,
+            name: None,
+        };
+        let client = Client::builder(transport.clone())
+            .roots(vec![root.clone()])
+            .on_log_message(move |message| {
+                let _ = log_tx.send(message.data);
+            })
+            .build();
+        tokio::spawn({
+            let client = client.clone();
+            async move { client.start().await }
+        });
+
+        let initialize = tokio::spawn({
+            let client = client.clone();
+            async move { client.initialize(Implementation::default()).await }
+        });
+        let server = Server::builder(http.accept().await.unwrap()).build();
+        tokio::spawn({
+            let server = server.clone();
+            async move { server.listen().await }
+        });
+        initialize.await??;
+        assert!(transport.session_id().is_some());
+        client.ping().await?;
+
+        // server initiated messages arrive on the GET stream
+        server
+            .logger()
+            .log(LoggingLevel::Info, "hello".into())
+            .await?;
+        assert_eq!(log_rx.recv().await.unwrap(), "hello");
+        assert_eq!(server.list_roots().await?, vec![root]);
+
+        transport.close().await?;
+        assert!(transport.receive().await?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_client_over_sse_responses() -> Result<()> {
+        test_client_over_http(StreamableHttpOptions::default()).await
+    }
+
+    #[tokio::test]
+    async fn test_client_over_json_responses() -> Result<()> {
+        test_client_over_http(StreamableHttpOptions::default().json_response(true)).await
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout_with_json_responses() -> Result<()> {
+        let options = StreamableHttpOptions::default().json_response(true);
+        let http = StreamableHttpServer::bind("127.0.0.1:0", options).await?;
+        let transport =
+            StreamableHttpClientTransport::new(&format!("http://{}/mcp", http.local_addr()))?;
+        let client = Client::builder(transport.clone()).build();
+        tokio::spawn({
+            let client = client.clone();
+            async move { client.start().await }
+        });
+        let initialize = tokio::spawn({
+            let client = client.clone();
+            async move { client.initialize(Implementation::default()).await }
+        });
+        let server = Server::builder(http.accept().await.unwrap())
+            .raw_request_handler("slow", |_| async {
+                tokio::time::sleep(Duration::from_secs(30)).await;
+                Ok(serde_json::json!({}))
+            })
+            .build();
+        tokio::spawn(async move { server.listen().await });
+        initialize.await??;
+
+        // the server only replies once the handler is done
+        let options = RequestOptions::default().timeout(Duration::from_millis(100));
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            client.request("slow", None, options),
+        )
+        .await?;
+        assert!(matches!(result, Err(McpError::Timeout)));
+        client.ping().await?;
+        transport.close().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,1062 @@
+use super::{
+    AllowedOrigins, HttpError, LAST_EVENT_ID_HEADER, SESSION_ID_HEADER, messages, parse_body,
+    parse_error, request_ids, response_ids,
+};
+use crate::transport::{JsonRpcMessage, Message, RequestId, Transport};
+use crate::types::CancelledNotification;
+use anyhow::Result;
+use async_trait::async_trait;
+use axum::{
+    Router,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::get,
+};
+use futures_util::StreamExt;
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, mpsc};
+use tokio::task::AbortHandle;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tracing::{debug, warn};
+
+/// Options of [`StreamableHttpServer`]
+#[derive(Debug, Clone)]
+pub struct StreamableHttpOptions {
+    path: String,
+    json_response: bool,
+    max_history: usize,
+    max_undelivered: usize,
+    session_idle_timeout: Option<Duration>,
+    allowed_origins: AllowedOrigins,
+}
+
+impl Default for StreamableHttpOptions {
+    fn default() -> Self {
+        Self {
+            path: "/mcp".to_string(),
+            json_response: false,
+            max_history: 1024,
+            max_undelivered: 1024,
+            session_idle_timeout: Some(Duration::from_secs(30 * 60)),
+            allowed_origins: AllowedOrigins::default(),
+        }
+    }
+}
+
+impl StreamableHttpOptions {
+    /// The path of the MCP endpoint, `/mcp` by default
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// Reply to POSTed requests with a plain JSON body instead of an SSE stream
+    /// server initiated messages then only go to the GET stream
+    pub fn json_response(mut self, json_response: bool) -> Self {
+        self.json_response = json_response;
+        self
+    }
+
+    /// Number of SSE events kept per session so clients can resume with `Last-Event-ID`
+    pub fn max_history(mut self, max_history: usize) -> Self {
+        self.max_history = max_history;
+        self
+    }
+
+    /// Number of server initiated messages kept per session while no stream is connected
+    /// the oldest message is dropped once the limit is reached
+    pub fn max_undelivered(mut self, max_undelivered: usize) -> Self {
+        self.max_undelivered = max_undelivered;
+        self
+    }
+
+    /// Remove sessions that had no request and no open stream for `timeout`, 30 minutes by default
+    /// `None` keeps sessions until the client deletes them or the transport is closed
+    pub fn session_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.session_idle_timeout = timeout;
+        self
+    }
+
+    /// Reject requests whose `Origin` header is not in `origins`
+    /// by default only pages served from localhost are allowed, guarding against DNS rebinding
+    pub fn allowed_origins(mut self, origins: Vec<String>) -> Self {
+        self.allowed_origins = AllowedOrigins::List(origins);
+        self
+    }
+
+    /// Accept requests from any `Origin`, e.g. for a public server that authenticates clients
+    pub fn allow_any_origin(mut self) -> Self {
+        self.allowed_origins = AllowedOrigins::Any;
+        self
+    }
+}
+
+/// Streamable HTTP server, each client session is handed out as a transport by [`accept`]
+///
+/// POST accepts JSON-RPC messages and answers requests with JSON or an SSE stream,
+/// GET opens a stream for server initiated messages and DELETE ends the session.
+///
+/// [`accept`]: StreamableHttpServer::accept
+pub struct StreamableHttpServer {
+    sessions: Mutex<mpsc::UnboundedReceiver<StreamableHttpServerTransport>>,
+    local_addr: SocketAddr,
+    task: AbortHandle,
+}
+
+impl StreamableHttpServer {
+    /// Listen on `addr` and serve the MCP endpoint
+    pub async fn bind(
+        addr: impl tokio::net::ToSocketAddrs,
+        options: StreamableHttpOptions,
+    ) -> Result<Self> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let (accept_tx, accept_rx) = mpsc::unbounded_channel();
+        let shared = Arc::new(Shared {
+            sessions: std::sync::Mutex::new(HashMap::new()),
+            accept: accept_tx,
+            options,
+        });
+        if let Some(timeout) = shared.options.session_idle_timeout {
+            tokio::spawn(expire_idle_sessions(Arc::downgrade(&shared), timeout));
+        }
+        let router = Router::new()
+            .route(
+                &shared.options.path,
+                get(handle_get).post(handle_post).delete(handle_delete),
+            )
+            .with_state(shared);
+        let task = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, router).await {
+                warn!("Streamable HTTP server failed: {e}");
+            }
+        })
+        .abort_handle();
+        debug!("Streamable HTTP server listening on {local_addr}");
+        Ok(Self {
+            sessions: Mutex::new(accept_rx),
+            local_addr,
+            task,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Wait for the next client to initialize a session
+    pub async fn accept(&self) -> Option<StreamableHttpServerTransport> {
+        self.sessions.lock().await.recv().await
+    }
+}
+
+impl Drop for StreamableHttpServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+struct Shared {
+    sessions: std::sync::Mutex<HashMap<String, Arc<Session>>>,
+    accept: mpsc::UnboundedSender<StreamableHttpServerTransport>,
+    options: StreamableHttpOptions,
+}
+
+impl Shared {
+    fn session(&self, headers: &HeaderMap) -> std::result::Result<Arc<Session>, HttpError> {
+        let Some(id) = headers
+            .get(SESSION_ID_HEADER)
+            .and_then(|id| id.to_str().ok())
+        else {
+            return Err(HttpError(
+                StatusCode::BAD_REQUEST,
+                "Missing Mcp-Session-Id header",
+            ));
+        };
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .inspect(|session| session.touch())
+            .ok_or(HttpError(StatusCode::NOT_FOUND, "Session not found"))
+    }
+
+    fn remove_session(&self, id: &str) {
+        if let Some(session) = self.sessions.lock().unwrap().remove(id) {
+            session.close();
+        }
+    }
+
+    fn check_origin(&self, headers: &HeaderMap) -> std::result::Result<(), HttpError> {
+        self.options.allowed_origins.check(headers)
+    }
+}
+
+/// Remove sessions idle for longer than `timeout`, until the server is dropped
+async fn expire_idle_sessions(shared: Weak<Shared>, timeout: Duration) {
+    let mut interval = tokio::time::interval((timeout / 2).max(Duration::from_millis(1)));
+    loop {
+        interval.tick().await;
+        let Some(shared) = shared.upgrade() else {
+            return;
+        };
+        let expired: Vec<_> = shared
+            .sessions
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|session| session.idle_for() >= timeout)
+            .map(|session| session.id.clone())
+            .collect();
+        for id in expired {
+            debug!("Session {id} expired");
+            shared.remove_session(&id);
+        }
+    }
+}
+
+type StreamId = u64;
+type EventId = u64;
+
+/// One client session, shared by the HTTP handlers and the session transport
+struct Session {
+    id: String,
+    incoming_tx: std::sync::Mutex<Option<mpsc::UnboundedSender<Message>>>,
+    incoming_rx: Mutex<mpsc::UnboundedReceiver<Message>>,
+    streams: std::sync::Mutex<Streams>,
+    max_history: usize,
+    max_undelivered: usize,
+    /// the last request of the client
+    last_activity: std::sync::Mutex<Instant>,
+}
+
+#[derive(Default)]
+struct Streams {
+    next_stream_id: StreamId,
+    next_event_id: EventId,
+    open: HashMap<StreamId, OpenStream>,
+    /// requests waiting for a response and the stream the response goes to
+    requests: HashMap<RequestId, StreamId>,
+    /// the stream opened with GET
+    standalone: Option<StreamId>,
+    /// events sent on SSE streams, replayed when a client resumes
+    history: VecDeque<(EventId, StreamId, Message)>,
+    /// server initiated messages sent while no stream was connected
+    undelivered: VecDeque<Message>,
+}
+
+struct OpenStream {
+    /// `None` while the client is disconnected
+    sender: Option<mpsc::UnboundedSender<(EventId, Message)>>,
+    /// number of responses still to send before the stream ends
+    remaining: usize,
+    json: bool,
+}
+
+impl Session {
+    fn new(id: String, options: &StreamableHttpOptions) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            id,
+            incoming_tx: std::sync::Mutex::new(Some(tx)),
+            incoming_rx: Mutex::new(rx),
+            streams: std::sync::Mutex::new(Streams::default()),
+            max_history: options.max_history,
+            max_undelivered: options.max_undelivered,
+            last_activity: std::sync::Mutex::new(Instant::now()),
+        }
+    }
+
+    fn touch(&self) {
+        *self.last_activity.lock().unwrap() = Instant::now();
+    }
+
+    /// Time since the last request, zero while the client has a stream open
+    fn idle_for(&self) -> Duration {
+        let streams = self.streams.lock().unwrap();
+        let connected = streams.open.values().any(|stream| {
+            stream
+                .sender
+                .as_ref()
+                .is_some_and(|sender| !sender.is_closed())
+        });
+        if connected {
+            return Duration::ZERO;
+        }
+        self.last_activity.lock().unwrap().elapsed()
+    }
+
+    /// Forward a message from the client to the server
+    fn receive(&self, message: Message) -> bool {
+        match &*self.incoming_tx.lock().unwrap() {
+            Some(tx) => tx.send(message).is_ok(),
+            None => false,
+        }
+    }
+
+    fn close(&self) {
+        self.incoming_tx.lock().unwrap().take();
+        let mut streams = self.streams.lock().unwrap();
+        streams.open.clear();
+        streams.standalone = None;
+    }
+
+    /// Open a stream for the responses to `requests`
+    fn open_stream(
+        &self,
+        requests: Vec<RequestId>,
+        json: bool,
+    ) -> mpsc::UnboundedReceiver<(EventId, Message)> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut streams = self.streams.lock().unwrap();
+        let stream_id = streams.next_stream_id;
+        streams.next_stream_id += 1;
+        streams.open.insert(
+            stream_id,
+            OpenStream {
+                sender: Some(tx),
+                remaining: requests.len(),
+                json,
+            },
+        );
+        for id in requests {
+            streams.requests.insert(id, stream_id);
+        }
+        rx
+    }
+
+    /// Open the GET stream, resuming after `last_event_id` if given
+    fn open_standalone(
+        &self,
+        last_event_id: Option<EventId>,
+    ) -> std::result::Result<mpsc::UnboundedReceiver<(EventId, Message)>, HttpError> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut streams = self.streams.lock().unwrap();
+        if let Some(last_event_id) = last_event_id {
+            let Some(stream_id) = streams
+                .history
+                .iter()
+                .find(|(event_id, _, _)| *event_id == last_event_id)
+                .map(|(_, stream_id, _)| *stream_id)
+            else {
+                return Err(HttpError(StatusCode::NOT_FOUND, "Unknown event id"));
+            };
+            for (event_id, _, message) in streams
+                .history
+                .iter()
+                .filter(|(event_id, id, _)| *id == stream_id && *event_id > last_event_id)
+            {
+                let _ = tx.send((*event_id, message.clone()));
+            }
+            // a finished stream is replayed and closed by dropping `tx`
+            if let Some(stream) = streams.open.get_mut(&stream_id) {
+                stream.sender = Some(tx);
+            }
+            return Ok(rx);
+        }
+        if let Some(stream) = streams.standalone.and_then(|id| streams.open.get(&id))
+            && stream.sender.as_ref().is_some_and(|tx| !tx.is_closed())
+        {
+            return Err(HttpError(
+                StatusCode::CONFLICT,
+                "A stream is already open for this session",
+            ));
+        }
+        let stream_id = match streams.standalone {
+            Some(stream_id) => stream_id,
+            None => {
+                let stream_id = streams.next_stream_id;
+                streams.next_stream_id += 1;
+                streams.standalone = Some(stream_id);
+                stream_id
+            }
+        };
+        streams.open.insert(
+            stream_id,
+            OpenStream {
+                sender: Some(tx),
+                remaining: 0,
+                json: false,
+            },
+        );
+        for message in std::mem::take(&mut streams.undelivered) {
+            streams.deliver(stream_id, message, self.max_history);
+        }
+        Ok(rx)
+    }
+
+    /// The client cancelled a request, it will not get a response
+    fn cancel(&self, request_id: &RequestId) {
+        let mut streams = self.streams.lock().unwrap();
+        if let Some(stream_id) = streams.requests.remove(request_id) {
+            streams.complete(stream_id, 1);
+        }
+    }
+
+    /// Send a message from the server to the stream it belongs to
+    fn send(&self, message: Message) {
+        let mut streams = self.streams.lock().unwrap();
+        let response_ids = response_ids(&message);
+        if response_ids.is_empty() {
+            match streams.server_stream() {
+                Some(stream_id) => streams.deliver(stream_id, message, self.max_history),
+                None => {
+                    if streams.undelivered.len() >= self.max_undelivered {
+                        warn!("Dropping undelivered message of session {}", self.id);
+                        streams.undelivered.pop_front();
+                    }
+                    if self.max_undelivered > 0 {
+                        streams.undelivered.push_back(message);
+                    }
+                }
+            }
+            return;
+        }
+        let stream_ids: Vec<_> = response_ids
+            .iter()
+            .filter_map(|id| streams.requests.remove(id))
+            .collect();
+        let Some(&stream_id) = stream_ids.first() else {
+            warn!("Dropping response to unknown request");
+            return;
+        };
+        streams.deliver(stream_id, message, self.max_history);
+        streams.complete(stream_id, stream_ids.len());
+    }
+}
+
+impl Streams {
+    /// The stream for server initiated requests and notifications
+    /// the GET stream if connected, otherwise the latest POST stream
+    fn server_stream(&self) -> Option<StreamId> {
+        let connected = |id: &StreamId| {
+            self.open
+                .get(id)
+                .is_some_and(|stream| !stream.json && stream.sender.is_some())
+        };
+        if let Some(id) = self.standalone.filter(connected) {
+            return Some(id);
+        }
+        self.open.keys().copied().filter(connected).max()
+    }
+
+    fn deliver(&mut self, stream_id: StreamId, message: Message, max_history: usize) {
+        let event_id = self.next_event_id;
+        self.next_event_id += 1;
+        let Some(stream) = self.open.get_mut(&stream_id) else {
+            return;
+        };
+        if let Some(sender) = &stream.sender
+            && sender.send((event_id, message.clone())).is_err()
+        {
+            // the client disconnected, it can resume from the history
+            stream.sender = None;
+        }
+        if !stream.json && max_history > 0 {
+            if self.history.len() >= max_history {
+                self.history.pop_front();
+            }
+            self.history.push_back((event_id, stream_id, message));
+        }
+    }
+
+    /// `count` responses were sent on the stream, end it once all were sent
+    fn complete(&mut self, stream_id: StreamId, count: usize) {
+        let Some(stream) = self.open.get_mut(&stream_id) else {
+            return;
+        };
+        stream.remaining = stream.remaining.saturating_sub(count);
+        if stream.remaining == 0 && self.standalone != Some(stream_id) {
+            self.open.remove(&stream_id);
+        }
+    }
+}
+
+/// Transport of a single session of a [`StreamableHttpServer`]
+/// the session is removed when the transport is closed or its last clone dropped
+#[derive(Clone)]
+pub struct StreamableHttpServerTransport {
+    session: Arc<Session>,
+    handle: Arc<SessionHandle>,
+}
+
+/// Removes the session from the server once all transports are gone
+struct SessionHandle {
+    id: String,
+    shared: Weak<Shared>,
+}
+
+impl Drop for SessionHandle {
+    fn drop(&mut self) {
+        if let Some(shared) = self.shared.upgrade() {
+            shared.remove_session(&self.id);
+        }
+    }
+}
+
+impl StreamableHttpServerTransport {
+    fn new(session: Arc<Session>, shared: &Arc<Shared>) -> Self {
+        let handle = Arc::new(SessionHandle {
+            id: session.id.clone(),
+            shared: Arc::downgrade(shared),
+        });
+        Self { session, handle }
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session.id
+    }
+}
+
+#[async_trait]
+impl Transport for StreamableHttpServerTransport {
+    async fn send(&self, message: &Message) -> Result<()> {
+        if self.session.incoming_tx.lock().unwrap().is_none() {
+            return Err(anyhow::anyhow!("Session {} closed", self.session.id));
+        }
+        self.session.send(message.clone());
+        Ok(())
+    }
+
+    async fn receive(&self) -> Result<Option<Message>> {
+        Ok(self.session.incoming_rx.lock().await.recv().await)
+    }
+
+    async fn open(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn close(&self) -> Result<()> {
+        if let Some(shared) = self.handle.shared.upgrade() {
+            shared.remove_session(&self.session.id);
+        }
+        self.session.close();
+        Ok(())
+    }
+}
+
+async fn handle_post(
+    State(shared): State<Arc<Shared>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if let Err(error) = shared.check_origin(&headers) {
+        return error.into_response();
+    }
+    if !accepts(&headers, "application/json") || !accepts(&headers, "text/event-stream") {
+        return HttpError(
+            StatusCode::NOT_ACCEPTABLE,
+            "Client must accept application/json and text/event-stream",
+        )
+        .into_response();
+    }
+    let is_json = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/json"));
+    if !is_json {
+        return HttpError(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            "Content-Type must be application/json",
+        )
+        .into_response();
+    }
+    let message = match parse_body(&body) {
+        Ok(message) => message,
+        Err(e) => return parse_error(e),
+    };
+    let session = if headers.contains_key(SESSION_ID_HEADER) {
+        let session = match shared.session(&headers) {
+            Ok(session) => session,
+            Err(error) => return error.into_response(),
+        };
+        if is_initialize(&message) {
+            return HttpError(StatusCode::BAD_REQUEST, "Session is already initialized")
+                .into_response();
+        }
+        session
+    } else if is_initialize(&message) {
+        let session = Arc::new(Session::new(
+            uuid::Uuid::new_v4().to_string(),
+            &shared.options,
+        ));
+        let transport = StreamableHttpServerTransport::new(session.clone(), &shared);
+        shared
+            .sessions
+            .lock()
+            .unwrap()
+            .insert(session.id.clone(), session.clone());
+        if shared.accept.send(transport).is_err() {
+            return HttpError(StatusCode::SERVICE_UNAVAILABLE, "Server is shutting down")
+                .into_response();
+        }
+        debug!("New session {}", session.id);
+        session
+    } else {
+        return HttpError(
+            StatusCode::BAD_REQUEST,
+            "Missing Mcp-Session-Id header, the first request must be initialize",
+        )
+        .into_response();
+    };
+
+    for request_id in cancelled_request_ids(&message) {
+        session.cancel(&request_id);
+    }
+    let requests = request_ids(&message);
+    if requests.is_empty() {
+        if !session.receive(message) {
+            return HttpError(StatusCode::NOT_FOUND, "Session closed").into_response();
+        }
+        return StatusCode::ACCEPTED.into_response();
+    }
+
+    let json = shared.options.json_response;
+    let mut events = session.open_stream(requests, json);
+    if !session.receive(message) {
+        return HttpError(StatusCode::NOT_FOUND, "Session closed").into_response();
+    }
+    let mut response = if json {
+        let mut messages = Vec::new();
+        while let Some((_, message)) = events.recv().await {
+            messages.push(message);
+        }
+        let body = match messages.len() {
+            1 => messages.remove(0),
+            _ => JsonRpcMessage::batch(messages),
+        };
+        axum::Json(body).into_response()
+    } else {
+        sse_response(events)
+    };
+    if let Ok(id) = HeaderValue::from_str(&session.id) {
+        response.headers_mut().insert(SESSION_ID_HEADER, id);
+    }
+    response
+}
+
+async fn handle_get(State(shared): State<Arc<Shared>>, headers: HeaderMap) -> Response {
+    if let Err(error) = shared.check_origin(&headers) {
+        return error.into_response();
+    }
+    if !accepts(&headers, "text/event-stream") {
+        return HttpError(
+            StatusCode::NOT_ACCEPTABLE,
+            "Client must accept text/event-stream",
+        )
+        .into_response();
+    }
+    let session = match shared.session(&headers) {
+        Ok(session) => session,
+        Err(error) => return error.into_response(),
+    };
+    let last_event_id = headers
+        .get(LAST_EVENT_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .and_then(|id| id.parse().ok());
+    match session.open_standalone(last_event_id) {
+        Ok(events) => sse_response(events),
+        Err(error) => error.into_response(),
+    }
+}
+
+async fn handle_delete(State(shared): State<Arc<Shared>>, headers: HeaderMap) -> Response {
+    if let Err(error) = shared.check_origin(&headers) {
+        return error.into_response();
+    }
+    match shared.session(&headers) {
+        Ok(session) => {
+            debug!("Session {} ended by the client", session.id);
+            shared.remove_session(&session.id);
+            StatusCode::OK.into_response()
+        }
+        Err(error) => error.into_response(),
+    }
+}
+
+fn sse_response(events: mpsc::UnboundedReceiver<(EventId, Message)>) -> Response {
+    let stream = UnboundedReceiverStream::new(events).map(|(event_id, message)| {
+        let data = serde_json::to_string(&message).unwrap_or_default();
+        Ok::<_, Infallible>(Event::default().id(event_id.to_string()).data(data))
+    });
+    Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// Whether the `Accept` header lists `media_type`
+fn accepts(headers: &HeaderMap, media_type: &str) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|accept| accept.to_str().ok())
+        .flat_map(|accept| accept.split(','))
+        .any(|accepted| accepted.split(';').next().unwrap_or_default().trim() == media_type)
+}
+
+fn is_initialize(message: &Message) -> bool {
+    messages(message).iter().any(
+        |message| matches!(message, JsonRpcMessage::Request(request) if request.method == "initialize"),
+    )
+}
+
+fn cancelled_request_ids(message: &Message) -> Vec<RequestId> {
+    messages(message)
+        .iter()
+        .filter_map(|message| match message {
+            JsonRpcMessage::Notification(notification)
+                if notification.method == "notifications/cancelled" =>
+            {
+                let params = notification.params.clone()?;
+                serde_json::from_value::<CancelledNotification>(params).ok()
+            }
+            _ => None,
+        })
+        .map(|cancelled| cancelled.request_id)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::http::sse::{SseEvent, SseParser};
+    use crate::transport::{JsonRpcNotification, JsonRpcRequest, JsonRpcResponse};
+    use serde_json::json;
+
+    const INITIALIZE: &str = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-03-26"}}"#;
+
+    fn post(client: &reqwest::Client, server: &StreamableHttpServer) -> reqwest::RequestBuilder {
+        client
+            .post(format!("http://{}/mcp", server.local_addr()))
+            .header(header::ACCEPT, "application/json, text/event-stream")
+            .header(header::CONTENT_TYPE, "application/json")
+    }
+
+    /// Reads the events of an SSE response
+    struct Events {
+        response: reqwest::Response,
+        parser: SseParser,
+        events: VecDeque<SseEvent>,
+    }
+
+    impl Events {
+        fn new(response: reqwest::Response) -> Self {
+            Self {
+                response,
+                parser: SseParser::default(),
+                events: VecDeque::new(),
+            }
+        }
+
+        async fn next(&mut self) -> Result<SseEvent> {
+            loop {
+                if let Some(event) = self.events.pop_front() {
+                    return Ok(event);
+                }
+                let chunk = self
+                    .response
+                    .chunk()
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("stream ended"))?;
+                self.events.extend(self.parser.feed(&chunk));
+            }
+        }
+
+        async fn next_message(&mut self) -> Result<Message> {
+            Ok(serde_json::from_str(&self.next().await?.data)?)
+        }
+    }
+
+    fn response(id: RequestId) -> Message {
+        JsonRpcMessage::Response(JsonRpcResponse {
+            id,
+            result: Some(json!({})),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_session_over_sse() -> Result<()> {
+        let server = StreamableHttpServer::bind("127.0.0.1:0", Default::default()).await?;
+        let client = reqwest::Client::new();
+
+        let initialize = post(&client, &server).body(INITIALIZE).send().await?;
+        assert_eq!(initialize.status(), StatusCode::OK);
+        let session_id = initialize.headers()[SESSION_ID_HEADER]
+            .to_str()?
+            .to_string();
+        let transport = server.accept().await.unwrap();
+        assert_eq!(transport.session_id(), session_id);
+
+        let Some(JsonRpcMessage::Request(request)) = transport.receive().await? else {
+            panic!("expected initialize request");
+        };
+        assert_eq!(request.method, "initialize");
+        transport.send(&response(request.id)).await?;
+        let message = Events::new(initialize).next_message().await?;
+        assert!(matches!(message, JsonRpcMessage::Response(r) if r.id == RequestId::Number(1)));
+
+        let initialized = post(&client, &server)
+            .header(SESSION_ID_HEADER, &session_id)
+            .body(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#)
+            .send()
+            .await?;
+        assert_eq!(initialized.status(), StatusCode::ACCEPTED);
+        assert!(matches!(
+            transport.receive().await?,
+            Some(JsonRpcMessage::Notification(_))
+        ));
+        let initialize_again = post(&client, &server)
+            .header(SESSION_ID_HEADER, &session_id)
+            .body(INITIALIZE)
+            .send()
+            .await?;
+        assert_eq!(initialize_again.status(), StatusCode::BAD_REQUEST);
+
+        // server initiated messages go to the GET stream
+        let stream = client
+            .get(format!("http://{}/mcp", server.local_addr()))
+            .header(header::ACCEPT, "text/event-stream")
+            .header(SESSION_ID_HEADER, &session_id)
+            .send()
+            .await?;
+        assert_eq!(stream.status(), StatusCode::OK);
+        let notification = JsonRpcMessage::Notification(JsonRpcNotification {
+            method: "notifications/tools/list_changed".to_string(),
+            ..Default::default()
+        });
+        transport.send(&notification).await?;
+        assert_eq!(Events::new(stream).next_message().await?, notification);
+
+        let deleted = client
+            .delete(format!("http://{}/mcp", server.local_addr()))
+            .header(SESSION_ID_HEADER, &session_id)
+            .send()
+            .await?;
+        assert_eq!(deleted.status(), StatusCode::OK);
+        assert!(transport.receive().await?.is_none());
+        let after_delete = post(&client, &server)
+            .header(SESSION_ID_HEADER, &session_id)
+            .body(r#"{"jsonrpc":"2.0","id":2,"method":"ping"}"#)
+            .send()
+            .await?;
+        assert_eq!(after_delete.status(), StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resume_with_last_event_id() -> Result<()> {
+        let server = StreamableHttpServer::bind("127.0.0.1:0", Default::default()).await?;
+        let client = reqwest::Client::new();
+        let initialize = post(&client, &server).body(INITIALIZE).send().await?;
+        let session_id = initialize.headers()[SESSION_ID_HEADER]
+            .to_str()?
+            .to_string();
+        let transport = server.accept().await.unwrap();
+        transport.receive().await?;
+
+        let call = post(&client, &server)
+            .header(SESSION_ID_HEADER, &session_id)
+            .body(r#"{"jsonrpc":"2.0","id":2,"method":"tools/call"}"#)
+            .send()
+            .await?;
+        transport.receive().await?;
+        // without a GET stream, progress goes to the stream of the request
+        let progress = JsonRpcMessage::Notification(JsonRpcNotification {
+            method: "notifications/progress".to_string(),
+            ..Default::default()
+        });
+        transport.send(&progress).await?;
+        let event = Events::new(call).next().await?;
+        assert_eq!(serde_json::from_str::<Message>(&event.data)?, progress);
+        // the client disconnects before the response
+
+        transport.send(&response(RequestId::Number(2))).await?;
+        let resumed = client
+            .get(format!("http://{}/mcp", server.local_addr()))
+            .header(header::ACCEPT, "text/event-stream")
+            .header(SESSION_ID_HEADER, &session_id)
+            .header(LAST_EVENT_ID_HEADER, event.id.unwrap())
+            .send()
+            .await?;
+        assert_eq!(
+            Events::new(resumed).next_message().await?,
+            response(RequestId::Number(2))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_json_response() -> Result<()> {
+        let options = StreamableHttpOptions::default().json_response(true);
+        let server = StreamableHttpServer::bind("127.0.0.1:0", options).await?;
+        let client = reqwest::Client::new();
+
+        let initialize = tokio::spawn(post(&client, &server).body(INITIALIZE).send());
+        let transport = server.accept().await.unwrap();
+        let Some(JsonRpcMessage::Request(JsonRpcRequest { id, .. })) = transport.receive().await?
+        else {
+            panic!("expected initialize request");
+        };
+        transport.send(&response(id)).await?;
+        let initialize = initialize.await??;
+        assert_eq!(
+            initialize.headers()[header::CONTENT_TYPE],
+            "application/json"
+        );
+        let message: Message = serde_json::from_slice(&initialize.bytes().await?)?;
+        assert!(matches!(message, JsonRpcMessage::Response(r) if r.id == RequestId::Number(1)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rejected_requests() -> Result<()> {
+        let server = StreamableHttpServer::bind("127.0.0.1:0", Default::default()).await?;
+        let client = reqwest::Client::new();
+
+        let without_session = post(&client, &server)
+            .body(r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#)
+            .send()
+            .await?;
+        assert_eq!(without_session.status(), StatusCode::BAD_REQUEST);
+        let malformed = post(&client, &server).body("{").send().await?;
+        assert_eq!(malformed.status(), StatusCode::BAD_REQUEST);
+        let invalid = post(&client, &server)
+            .body(r#"{"jsonrpc":"2.0","id":7,"method":5}"#)
+            .send()
+            .await?;
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+        let response: JsonRpcResponse = serde_json::from_slice(&invalid.bytes().await?)?;
+        assert_eq!(response.id, RequestId::Number(7));
+        let foreign_origin = post(&client, &server)
+            .header(header::ORIGIN, "http://evil.example")
+            .body(INITIALIZE)
+            .send()
+            .await?;
+        assert_eq!(foreign_origin.status(), StatusCode::FORBIDDEN);
+        let url = format!("http://{}/mcp", server.local_addr());
+        let json_only = client
+            .post(&url)
+            .header(header::ACCEPT, "application/json")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(INITIALIZE)
+            .send()
+            .await?;
+        assert_eq!(json_only.status(), StatusCode::NOT_ACCEPTABLE);
+        let not_json = client
+            .post(&url)
+            .header(header::ACCEPT, "application/json, text/event-stream")
+            .header(header::CONTENT_TYPE, "text/plain")
+            .body(INITIALIZE)
+            .send()
+            .await?;
+        assert_eq!(not_json.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        let local_origin = post(&client, &server)
+            .header(header::ORIGIN, "http://localhost:3000")
+            .body(INITIALIZE)
+            .send()
+            .await?;
+        assert_eq!(local_origin.status(), StatusCode::OK);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_allowed_origins() -> Result<()> {
+        let options =
+            StreamableHttpOptions::default().allowed_origins(vec!["https://app.example".into()]);
+        let server = StreamableHttpServer::bind("127.0.0.1:0", options).await?;
+        let client = reqwest::Client::new();
+        for (origin, status) in [
+            ("https://app.example", StatusCode::OK),
+            ("http://localhost:3000", StatusCode::FORBIDDEN),
+        ] {
+            let response = post(&client, &server)
+                .header(header::ORIGIN, origin)
+                .body(INITIALIZE)
+                .send()
+                .await?;
+            assert_eq!(response.status(), status);
+        }
+
+        let options = StreamableHttpOptions::default().allow_any_origin();
+        let server = StreamableHttpServer::bind("127.0.0.1:0", options).await?;
+        let response = post(&client, &server)
+            .header(header::ORIGIN, "http://evil.example")
+            .body(INITIALIZE)
+            .send()
+            .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_idle_session_expires() -> Result<()> {
+        let options =
+            StreamableHttpOptions::default().session_idle_timeout(Some(Duration::from_millis(100)));
+        let server = StreamableHttpServer::bind("127.0.0.1:0", options).await?;
+        let client = reqwest::Client::new();
+        let initialize = post(&client, &server).body(INITIALIZE).send().await?;
+        let session_id = initialize.headers()[SESSION_ID_HEADER]
+            .to_str()?
+            .to_string();
+        let transport = server.accept().await.unwrap();
+        transport.receive().await?;
+        transport.send(&response(RequestId::Number(1))).await?;
+        Events::new(initialize).next_message().await?;
+
+        let closed = tokio::time::timeout(Duration::from_secs(5), transport.receive()).await?;
+        assert!(closed?.is_none());
+        let after_expiry = post(&client, &server)
+            .header(SESSION_ID_HEADER, &session_id)
+            .body(r#"{"jsonrpc":"2.0","id":2,"method":"ping"}"#)
+            .send()
+            .await?;
+        assert_eq!(after_expiry.status(), StatusCode::NOT_FOUND);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_undelivered_messages_are_bounded() -> Result<()> {
+        let options = StreamableHttpOptions::default()
+            .json_response(true)
+            .max_undelivered(2);
+        let server = StreamableHttpServer::bind("127.0.0.1:0", options).await?;
+        let client = reqwest::Client::new();
+        let initialize = tokio::spawn(post(&client, &server).body(INITIALIZE).send());
+        let transport = server.accept().await.unwrap();
+        transport.receive().await?;
+        transport.send(&response(RequestId::Number(1))).await?;
+        initialize.await??;
+
+        let notification = |method: &str| {
+            JsonRpcMessage::Notification(JsonRpcNotification {
+                method: method.to_string(),
+                ..Default::default()
+            })
+        };
+        for method in ["first", "second", "third"] {
+            transport.send(&notification(method)).await?;
+        }
+        let stream = client
+            .get(format!("http://{}/mcp", server.local_addr()))
+            .header(header::ACCEPT, "text/event-stream")
+            .header(SESSION_ID_HEADER, transport.session_id())
+            .send()
+            .await?;
+        let mut events = Events::new(stream);
+        assert_eq!(events.next_message().await?, notification("second"));
+        assert_eq!(events.next_message().await?, notification("third"));
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+//! In memory transport connecting a client and a server in the same process
+//! e.g. to test tools or embed a server without spawning a subprocess
+use super::{Message, Transport};
+use crate::error::McpError;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::sync::{Mutex, mpsc};
+use tokio_util::sync::CancellationToken;
+
+/// One end of an in memory connection created by [`pair`]
+/// clones share the connection, closing any of them closes it for both ends
+#[derive(Clone)]
+pub struct MemoryTransport {
+    incoming: Arc<Mutex<mpsc::UnboundedReceiver<Message>>>,
+    outgoing: mpsc::UnboundedSender<Message>,
+    closed: CancellationToken,
+}
+
+/// Two connected transports, messages sent on one are received by the other
+pub fn pair() -> (MemoryTransport, MemoryTransport) {
+    let (a_tx, a_rx) = mpsc::unbounded_channel();
+    let (b_tx, b_rx) = mpsc::unbounded_channel();
+    let closed = CancellationToken::new();
+    (
+        MemoryTransport {
+            incoming: Arc::new(Mutex::new(a_rx)),
+            outgoing: b_tx,
+            closed: closed.clone(),
+        },
+        MemoryTransport {
+            incoming: Arc::new(Mutex::new(b_rx)),
+            outgoing: a_tx,
+            closed,
+        },
+    )
+}
+
+#[async_trait]
+impl Transport for MemoryTransport {
+    async fn send(&self, message: &Message) -> Result<()> {
+        if self.closed.is_cancelled() || self.outgoing.send(message.clone()).is_err() {
+            return Err(McpError::ConnectionClosed.into());
+        }
+        Ok(())
+    }
+
+    /// Returns `None` once the connection is closed or the other end dropped
+    async fn receive(&self) -> Result<Option<Message>> {
+        let mut incoming = self.incoming.lock().await;
+        tokio::select! {
+            message = incoming.recv() => Ok(message),
+            _ = self.closed.cancelled() => Ok(None),
+        }
+    }
+
+    async fn open(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.closed.cancel();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Client;
+    use crate::server::Server;
+    use crate::types::Implementation;
+
+    #[tokio::test]
+    async fn test_pair() -> Result<()> {
+        let (client_transport, server_transport) = pair();
+        let server = Server::builder(server_transport.clone()).build();
+        tokio::spawn(async move { server.listen().await });
+        let client = Client::builder(client_transport.clone()).build();
+        tokio::spawn({
+            let client = client.clone();
+            async move { client.start().await }
+        });
+        client.initialize(Implementation::default()).await?;
+        client.ping().await?;
+
+        client_transport.close().await?;
+        assert!(server_transport.receive().await?.is_none());
+        assert!(client.ping().await.is_err());
+        Ok(())
+    }
+}
@@ -3,32 +3,88 @@
 //! handles send and receive of messages
 //! defines transport layer types
 use anyhow::Result;
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
+pub mod memory;
 mod stdio;
 pub use stdio::*;
 
+#[cfg(feature = "http")]
+mod http;
+#[cfg(feature = "http")]
+pub use http::*;
+
+#[cfg(feature = "websocket")]
+mod websocket;
+#[cfg(feature = "websocket")]
+pub use websocket::*;
+
 /// only JsonRpcMessage is supported for now
 /// https://spec.modelcontextprotocol.io/specification/basic/messages/
 pub type Message = JsonRpcMessage;
 
+#[async_trait]
 pub trait Transport: Send + Sync + 'static {
     /// Send a message to the transport
-    fn send(&self, message: &Message) -> Result<()>;
+    async fn send(&self, message: &Message) -> Result<()>;
 
     /// Receive a message from the transport
-    /// this is blocking call
-    fn receive(&self) -> Result<Message>;
+    /// returns `None` once the peer has closed the connection
+    /// implementations should be cancel safe so that `receive` can be used in `select!`
+    async fn receive(&self) -> Result<Option<Message>>;
 
     /// open the transport
-    fn open(&self) -> Result<()>;
+    async fn open(&self) -> Result<()>;
 
     /// Close the transport
-    fn close(&self) -> Result<()>;
+    async fn close(&self) -> Result<()>;
+}
+
+/// Request ID type, JSON-RPC allows both numbers and strings
+/// the id is echoed back unchanged in the response
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(untagged)]
+pub enum RequestId {
+    Number(i64),
+    String(String),
+    /// only used in error responses when the request id could not be determined
+    Null,
+}
+
+impl Default for RequestId {
+    fn default() -> Self {
+        RequestId::Number(0)
+    }
+}
+
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestId::Number(n) => write!(f, "{n}"),
+            RequestId::String(s) => write!(f, "{s}"),
+            RequestId::Null => write!(f, "null"),
+        }
+    }
+}
+
+impl From<i64> for RequestId {
+    fn from(id: i64) -> Self {
+        RequestId::Number(id)
+    }
+}
+
+impl From<String> for RequestId {
+    fn from(id: String) -> Self {
+        RequestId::String(id)
+    }
 }
 
-/// Request ID type
-pub type RequestId = u64;
+impl From<&str> for RequestId {
+    fn from(id: &str) -> Self {
+        RequestId::String(id.to_string())
+    }
+}
 /// JSON RPC version type
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(transparent)]
@@ -50,11 +106,80 @@ impl JsonRpcVersion {
 #[serde(deny_unknown_fields)]
 #[serde(untagged)]
 pub enum JsonRpcMessage {
+    /// A JSON-RPC batch, an array of requests and notifications or of responses
+    /// tried first as the structs below would also accept an array
+    /// elements are kept as json so that an invalid one does not fail the whole batch,
+    /// see [`JsonRpcMessage::batch`] and [`parse_batch_element`]
+    Batch(Vec<serde_json::Value>),
     Response(JsonRpcResponse),
     Request(JsonRpcRequest),
     Notification(JsonRpcNotification),
 }
 
+impl JsonRpcMessage {
+    /// A batch of `messages`
+    pub fn batch(messages: impl IntoIterator<Item = JsonRpcMessage>) -> Self {
+        JsonRpcMessage::Batch(
+            messages
+                .into_iter()
+                .map(|message| serde_json::to_value(message).expect("messages serialize to json"))
+                .collect(),
+        )
+    }
+}
+
+/// Parse a single element of a batch
+/// an invalid element gets the returned error response, with its id if it has a valid one
+pub fn parse_batch_element(
+    element: serde_json::Value,
+) -> std::result::Result<JsonRpcMessage, Box<JsonRpcResponse>> {
+    let id = element
+        .get("id")
+        .and_then(|id| serde_json::from_value(id.clone()).ok())
+        .unwrap_or(RequestId::Null);
+    let error = |message: String| {
+        Box::new(JsonRpcResponse {
+            id: id.clone(),
+            error: Some(JsonRpcError {
+                code: crate::types::ErrorCode::InvalidRequest as i32,
+                message,
+                data: None,
+            }),
+            ..Default::default()
+        })
+    };
+    if element.is_array() {
+        return Err(error("Nested batch".to_string()));
+    }
+    serde_json::from_value(element).map_err(|e| error(format!("Invalid Request: {e}")))
+}
+
+/// Parse a message received as json text
+/// like batch elements, a message that is not valid JSON-RPC fails with [`InvalidMessage`]
+/// holding the error response, with the id of the message if it has a valid one
+pub fn parse_message(json: &str) -> Result<Message> {
+    let value: serde_json::Value = serde_json::from_str(json)?;
+    if let serde_json::Value::Array(elements) = value {
+        return Ok(JsonRpcMessage::Batch(elements));
+    }
+    parse_batch_element(value).map_err(|response| InvalidMessage(response).into())
+}
+
+/// Valid json that is not a valid JSON-RPC message, see [`parse_message`]
+#[derive(Debug, Clone)]
+pub struct InvalidMessage(pub Box<JsonRpcResponse>);
+
+impl std::fmt::Display for InvalidMessage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.0.error {
+            Some(error) => write!(f, "{}", error.message),
+            None => write!(f, "Invalid Request"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidMessage {}
+
 // json rpc types
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
@@ -94,6 +219,17 @@ pub struct JsonRpcResponse {
     pub jsonrpc: JsonRpcVersion,
 }
 
+impl JsonRpcResponse {
+    /// The result of the request, or the error returned by the peer
+    /// a missing result is `null`
+    pub fn into_result(self) -> std::result::Result<serde_json::Value, crate::error::McpError> {
+        match self.error {
+            Some(error) => Err(error.into()),
+            None => Ok(self.result.unwrap_or_default()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 #[serde(default)]
@@ -118,7 +254,7 @@ mod tests {
         match message {
             JsonRpcMessage::Request(req) => {
                 assert_eq!(req.jsonrpc.as_str(), "2.0");
-                assert_eq!(req.id, 0);
+                assert_eq!(req.id, RequestId::Number(0));
                 assert_eq!(req.method, "initialize");
 
                 // Verify params exist and are an object
@@ -135,4 +271,115 @@ mod tests {
             _ => panic!("Expected Request variant"),
         }
     }
+
+    #[test]
+    fn test_string_request_id_round_trip() {
+        let json = r#"{"jsonrpc":"2.0","id":"abc-1","method":"ping"}"#;
+        let message: Message = serde_json::from_str(json).unwrap();
+        let JsonRpcMessage::Request(req) = message else {
+            panic!("Expected Request variant");
+        };
+        assert_eq!(req.id, RequestId::from("abc-1"));
+
+        let response = JsonRpcMessage::Response(JsonRpcResponse {
+            id: req.id,
+            result: Some(serde_json::json!({})),
+            ..Default::default()
+        });
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json["id"], "abc-1");
+        let parsed: Message = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, response);
+    }
+
+    #[test]
+    fn test_numeric_request_id_stays_numeric() {
+        let json = r#"{"jsonrpc":"2.0","id":42,"result":{}}"#;
+        let message: Message = serde_json::from_str(json).unwrap();
+        let JsonRpcMessage::Response(resp) = message else {
+            panic!("Expected Response variant");
+        };
+        assert_eq!(resp.id, RequestId::Number(42));
+        assert_eq!(serde_json::to_value(&resp).unwrap()["id"], 42);
+    }
+
+    #[test]
+    fn test_batch_round_trip() {
+        let json = r#"[{"jsonrpc":"2.0","id":1,"method":"ping"},{"jsonrpc":"2.0","method":"notifications/initialized"}]"#;
+        let message: Message = serde_json::from_str(json).unwrap();
+        let JsonRpcMessage::Batch(elements) = &message else {
+            panic!("Expected Batch variant");
+        };
+        let messages: Vec<_> = elements
+            .iter()
+            .map(|element| parse_batch_element(element.clone()).unwrap())
+            .collect();
+        assert!(matches!(messages[0], JsonRpcMessage::Request(_)));
+        assert!(matches!(messages[1], JsonRpcMessage::Notification(_)));
+        assert_eq!(JsonRpcMessage::batch(messages), message);
+
+        let serialized = serde_json::to_value(&message).unwrap();
+        assert!(serialized.is_array());
+        assert_eq!(
+            serde_json::from_value::<Message>(serialized).unwrap(),
+            message
+        );
+    }
+
+    #[test]
+    fn test_null_id_error_response() {
+        let response = JsonRpcResponse {
+            id: RequestId::Null,
+            error: Some(JsonRpcError {
+                code: -32600,
+                message: "Invalid Request".to_string(),
+                data: None,
+            }),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json["id"].is_null());
+        assert_eq!(
+            serde_json::from_value::<JsonRpcResponse>(json).unwrap(),
+            response
+        );
+    }
+
+    #[test]
+    fn test_invalid_batch_element() {
+        let json =
+            r#"[{"jsonrpc":"2.0","id":1,"method":"ping"},{"jsonrpc":"2.0","id":2,"foo":1},3,[]]"#;
+        let JsonRpcMessage::Batch(elements) = serde_json::from_str(json).unwrap() else {
+            panic!("Expected Batch variant");
+        };
+        let results: Vec<_> = elements.into_iter().map(parse_batch_element).collect();
+        assert!(matches!(results[0], Ok(JsonRpcMessage::Request(_))));
+        let ids: Vec<_> = results[1..]
+            .iter()
+            .map(|result| result.clone().unwrap_err().id)
+            .collect();
+        assert_eq!(
+            ids,
+            vec![RequestId::Number(2), RequestId::Null, RequestId::Null]
+        );
+    }
+
+    #[test]
+    fn test_parse_message() {
+        let message = parse_message(r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#).unwrap();
+        assert!(matches!(message, JsonRpcMessage::Request(_)));
+        let message = parse_message(r#"[{"jsonrpc":"2.0","method":"ping"}]"#).unwrap();
+        assert!(matches!(message, JsonRpcMessage::Batch(elements) if elements.len() == 1));
+
+        let error = parse_message(r#"{"jsonrpc":"2.0","id":"a","method":5}"#).unwrap_err();
+        let InvalidMessage(response) = error.downcast().unwrap();
+        assert_eq!(response.id, RequestId::from("a"));
+        assert!(parse_message("{").unwrap_err().is::<serde_json::Error>());
+    }
+
+    #[test]
+    fn test_empty_batch() {
+        let message: Message = serde_json::from_str("[]").unwrap();
+        assert_eq!(message, JsonRpcMessage::Batch(vec![]));
+    }
 }
@@ -1,42 +1,89 @@
-use super::{Message, Transport};
+use super::{Message, Transport, parse_message};
 use anyhow::Result;
-use std::io::{self, BufRead, Write};
-use std::process::{Child, Command, Stdio};
-use std::sync::{Arc, Mutex};
+use async_trait::async_trait;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{self, AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader, BufWriter};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::Mutex;
+use tokio::time::timeout;
 use tracing::debug;
 
+/// Reads newline delimited messages from an async reader
+/// partially read lines are kept in `buf` so a cancelled read can be resumed
+struct LineReader<R> {
+    reader: BufReader<R>,
+    buf: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> LineReader<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            buf: Vec::new(),
+        }
+    }
+
+    async fn next_line(&mut self) -> Result<Option<String>> {
+        loop {
+            let n = self.reader.read_until(b'\n', &mut self.buf).await?;
+            if n == 0 && self.buf.is_empty() {
+                return Ok(None);
+            }
+            let line = String::from_utf8(std::mem::take(&mut self.buf))?;
+            // skip blank lines between messages
+            if !line.trim().is_empty() {
+                return Ok(Some(line));
+            }
+        }
+    }
+}
+
 /// Stdio transport for server with json serialization
 /// TODO: support for other binary serialzation formats
-#[derive(Default, Clone)]
-pub struct ServerStdioTransport;
+#[derive(Clone)]
+pub struct ServerStdioTransport {
+    stdin: Arc<Mutex<LineReader<io::Stdin>>>,
+    stdout: Arc<Mutex<io::Stdout>>,
+}
 
+impl Default for ServerStdioTransport {
+    fn default() -> Self {
+        Self {
+            stdin: Arc::new(Mutex::new(LineReader::new(io::stdin()))),
+            stdout: Arc::new(Mutex::new(io::stdout())),
+        }
+    }
+}
+
+#[async_trait]
 impl Transport for ServerStdioTransport {
-    fn receive(&self) -> Result<Message> {
-        let stdin = io::stdin();
-        let mut reader = stdin.lock();
-        let mut line = String::new();
-        reader.read_line(&mut line)?;
+    async fn receive(&self) -> Result<Option<Message>> {
+        let mut stdin = self.stdin.lock().await;
+        let Some(line) = stdin.next_line().await? else {
+            return Ok(None);
+        };
         debug!("Received: {line}");
-        let message: Message = serde_json::from_str(&line)?;
-        Ok(message)
+        let message = parse_message(&line)?;
+        Ok(Some(message))
     }
 
-    fn send(&self, message: &Message) -> Result<()> {
-        let stdout = io::stdout();
-        let mut writer = stdout.lock();
+    async fn send(&self, message: &Message) -> Result<()> {
+        let mut writer = self.stdout.lock().await;
         let serialized = serde_json::to_string(message)?;
         debug!("Sending: {serialized}");
-        writer.write_all(serialized.as_bytes())?;
-        writer.write_all(b"\n")?;
-        writer.flush()?;
+        writer.write_all(serialized.as_bytes()).await?;
+        writer.write_all(b"\n").await?;
+        writer.flush().await?;
         Ok(())
     }
 
-    fn open(&self) -> Result<()> {
+    async fn open(&self) -> Result<()> {
         Ok(())
     }
 
-    fn close(&self) -> Result<()> {
+    async fn close(&self) -> Result<()> {
         Ok(())
     }
 }
@@ -44,8 +91,8 @@ impl Transport for ServerStdioTransport {
 /// ClientStdioTransport launches a child process and communicates with it via stdio
 #[derive(Clone)]
 pub struct ClientStdioTransport {
-    stdin: Arc<Mutex<Option<io::BufWriter<std::process::ChildStdin>>>>,
-    stdout: Arc<Mutex<Option<io::BufReader<std::process::ChildStdout>>>>,
+    stdin: Arc<Mutex<Option<BufWriter<ChildStdin>>>>,
+    stdout: Arc<Mutex<Option<LineReader<ChildStdout>>>>,
     child: Arc<Mutex<Option<Child>>>,
     program: String,
     args: Vec<String>,
@@ -63,43 +110,40 @@ impl ClientStdioTransport {
     }
 }
 
+#[async_trait]
 impl Transport for ClientStdioTransport {
-    fn receive(&self) -> Result<Message> {
-        let mut stdout = self
-            .stdout
-            .lock()
-            .map_err(|e| anyhow::anyhow!("Mutex lock failed: {}", e))?;
+    async fn receive(&self) -> Result<Option<Message>> {
+        let mut stdout = self.stdout.lock().await;
         let stdout = stdout
             .as_mut()
             .ok_or_else(|| anyhow::anyhow!("Transport not opened"))?;
-        let mut line = String::new();
-        stdout.read_line(&mut line)?;
-        println!("Received from process: {line}");
-        let message: Message = serde_json::from_str(&line)?;
-        Ok(message)
+        let Some(line) = stdout.next_line().await? else {
+            return Ok(None);
+        };
+        debug!("Received from process: {line}");
+        let message = parse_message(&line)?;
+        Ok(Some(message))
     }
 
-    fn send(&self, message: &Message) -> Result<()> {
-        let mut stdin = self
-            .stdin
-            .lock()
-            .map_err(|e| anyhow::anyhow!("Mutex lock failed: {}", e))?;
+    async fn send(&self, message: &Message) -> Result<()> {
+        let mut stdin = self.stdin.lock().await;
         let stdin = stdin
             .as_mut()
             .ok_or_else(|| anyhow::anyhow!("Transport not opened"))?;
         let serialized = serde_json::to_string(message)?;
         debug!("Sending to process: {serialized}");
-        stdin.write_all(serialized.as_bytes())?;
-        stdin.write_all(b"\n")?;
-        stdin.flush()?;
+        stdin.write_all(serialized.as_bytes()).await?;
+        stdin.write_all(b"\n").await?;
+        stdin.flush().await?;
         Ok(())
     }
 
-    fn open(&self) -> Result<()> {
+    async fn open(&self) -> Result<()> {
         let mut child = Command::new(&self.program)
             .args(&self.args)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
+            .kill_on_drop(true)
             .spawn()?;
 
         let stdin = child
@@ -111,66 +155,50 @@ impl Transport for ClientStdioTransport {
             .take()
             .ok_or_else(|| anyhow::anyhow!("Child process stdout not available"))?;
 
-        *self.stdin.lock().unwrap() = Some(io::BufWriter::new(stdin));
-        *self.stdout.lock().unwrap() = Some(io::BufReader::new(stdout));
-        *self.child.lock().unwrap() = Some(child);
+        *self.stdin.lock().await = Some(BufWriter::new(stdin));
+        *self.stdout.lock().await = Some(LineReader::new(stdout));
+        *self.child.lock().await = Some(child);
 
         Ok(())
     }
 
     /// Attempts graceful shutdown with timeouts
-    fn close(&self) -> Result<()> {
+    async fn close(&self) -> Result<()> {
         const GRACEFUL_TIMEOUT_MS: u64 = 1000;
-        const SIGTERM_TIMEOUT_MS: u64 = 500;
+        const KILL_TIMEOUT_MS: u64 = 500;
 
         // Drop stdin to close input stream
         {
-            let mut stdin_guard = self
-                .stdin
-                .lock()
-                .map_err(|e| anyhow::anyhow!("Mutex lock failed: {}", e))?;
+            let mut stdin_guard = self.stdin.lock().await;
             if let Some(stdin) = stdin_guard.as_mut() {
-                stdin.flush()?;
+                stdin.flush().await?;
             }
             *stdin_guard = None;
         }
 
         // Get child process handle
-        let mut child_guard = self
-            .child
-            .lock()
-            .map_err(|e| anyhow::anyhow!("Mutex lock failed: {}", e))?;
+        let mut child_guard = self.child.lock().await;
 
         let Some(child) = child_guard.as_mut() else {
             return Ok(()); // Already closed
         };
 
         // Wait for graceful shutdown
-        match child.try_wait()? {
-            Some(_) => {
-                *child_guard = None;
-                return Ok(()); // Process already exited
-            }
-            None => {
-                std::thread::sleep(std::time::Duration::from_millis(GRACEFUL_TIMEOUT_MS));
+        if timeout(Duration::from_millis(GRACEFUL_TIMEOUT_MS), child.wait())
+            .await
+            .is_err()
+        {
+            // Kill if still running, on Unix this sends SIGKILL
+            debug!("Process did not exit gracefully, killing it");
+            child.start_kill()?;
+            if timeout(Duration::from_millis(KILL_TIMEOUT_MS), child.wait())
+                .await
+                .is_err()
+            {
+                debug!("Process did not respond to kill");
             }
         }
 
-        // Send SIGTERM if still running
-        if child.try_wait()?.is_none() {
-            debug!("Process did not exit gracefully, sending SIGTERM");
-            child.kill()?; // On Unix this sends SIGTERM
-            std::thread::sleep(std::time::Duration::from_millis(SIGTERM_TIMEOUT_MS));
-        }
-
-        // Force kill if still running
-        if child.try_wait()?.is_none() {
-            debug!("Process did not respond to SIGTERM, forcing kill");
-            child.kill()?;
-        }
-
-        // Wait for final exit
-        child.wait()?;
         *child_guard = None;
         Ok(())
     }
@@ -182,35 +210,46 @@ mod tests {
 
     use super::*;
 
-    #[test]
+    #[tokio::test]
     #[cfg(unix)]
-    fn test_stdio_transport() -> Result<()> {
+    async fn test_stdio_transport() -> Result<()> {
         // Create transport connected to cat command which will stay alive
         let transport = ClientStdioTransport::new("cat", &[])?;
 
         // Create a test message
         let test_message = JsonRpcMessage::Request(JsonRpcRequest {
-            id: 1,
+            id: 1.into(),
             method: "test".to_string(),
             params: Some(serde_json::json!({"hello": "world"})),
             jsonrpc: JsonRpcVersion::default(),
         });
 
         // Open transport
-        transport.open()?;
+        transport.open().await?;
 
         // Send message
-        transport.send(&test_message)?;
+        transport.send(&test_message).await?;
 
         // Receive echoed message
-        let response = transport.receive()?;
+        let response = transport.receive().await?;
 
         // Verify the response matches
-        assert_eq!(test_message, response);
+        assert_eq!(Some(test_message), response);
 
         // Clean up
-        transport.close()?;
+        transport.close().await?;
 
         Ok(())
     }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn test_stdio_transport_eof() -> Result<()> {
+        // `true` exits immediately, closing its stdout
+        let transport = ClientStdioTransport::new("true", &[])?;
+        transport.open().await?;
+        assert_eq!(None, transport.receive().await?);
+        transport.close().await?;
+        Ok(())
+    }
 }
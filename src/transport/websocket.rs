@@ -0,0 +1,385 @@
+//! WebSocket transport carrying one JSON-RPC message per text frame
+use super::{Message, Transport, parse_message};
+use crate::error::McpError;
+use crate::types::ErrorCode;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{Mutex, mpsc};
+use tokio::task::AbortHandle;
+use tokio::time::{Interval, MissedTickBehavior};
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::Message as Frame;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+/// Options of WebSocket connections
+#[derive(Debug, Clone)]
+pub struct WebSocketOptions {
+    ping_interval: Option<Duration>,
+}
+
+impl Default for WebSocketOptions {
+    fn default() -> Self {
+        Self {
+            ping_interval: Some(Duration::from_secs(30)),
+        }
+    }
+}
+
+impl WebSocketOptions {
+    /// Ping the peer every `interval`, the connection is closed
+    /// when no pong arrived before the next ping
+    pub fn ping_interval(mut self, interval: Option<Duration>) -> Self {
+        self.ping_interval = interval;
+        self
+    }
+}
+
+/// WebSocket transport for clients and servers
+///
+/// A close frame with a code other than normal or going away, a failed connection
+/// or a missing pong is received as an error with code [`ErrorCode::ConnectionClosed`].
+#[derive(Clone)]
+pub struct WebSocketTransport {
+    outgoing: mpsc::UnboundedSender<Message>,
+    incoming: Arc<Mutex<mpsc::UnboundedReceiver<Result<Message>>>>,
+    closed: CancellationToken,
+}
+
+impl WebSocketTransport {
+    /// Connect to the WebSocket MCP server at `url`
+    pub async fn connect(url: &str, options: WebSocketOptions) -> Result<Self> {
+        let (ws, _) = tokio_tungstenite::connect_async(url).await?;
+        debug!("Connected to {url}");
+        Ok(Self::from_stream(ws, options))
+    }
+
+    /// Use an established WebSocket, e.g. one upgraded by an existing HTTP server
+    pub fn from_stream<S>(ws: WebSocketStream<S>, options: WebSocketOptions) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let (outgoing_tx, outgoing_rx) = mpsc::unbounded_channel();
+        let (incoming_tx, incoming_rx) = mpsc::unbounded_channel();
+        let closed = CancellationToken::new();
+        let ping = options.ping_interval.map(|period| {
+            let mut interval = tokio::time::interval(period);
+            // a late tick must not count as a second missed pong
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval.reset();
+            interval
+        });
+        tokio::spawn(run(ws, outgoing_rx, incoming_tx, closed.clone(), ping));
+        Self {
+            outgoing: outgoing_tx,
+            incoming: Arc::new(Mutex::new(incoming_rx)),
+            closed,
+        }
+    }
+}
+
+#[async_trait]
+impl Transport for WebSocketTransport {
+    async fn send(&self, message: &Message) -> Result<()> {
+        if self.closed.is_cancelled() || self.outgoing.send(message.clone()).is_err() {
+            return Err(McpError::ConnectionClosed.into());
+        }
+        Ok(())
+    }
+
+    async fn receive(&self) -> Result<Option<Message>> {
+        self.incoming.lock().await.recv().await.transpose()
+    }
+
+    async fn open(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Send a normal close frame
+    async fn close(&self) -> Result<()> {
+        self.closed.cancel();
+        Ok(())
+    }
+}
+
+/// Pump frames between the socket and the transport channels until the connection ends
+async fn run<S>(
+    ws: WebSocketStream<S>,
+    mut outgoing: mpsc::UnboundedReceiver<Message>,
+    incoming: mpsc::UnboundedSender<Result<Message>>,
+    closed: CancellationToken,
+    mut ping: Option<Interval>,
+) where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (mut sink, mut stream) = ws.split();
+    let mut awaiting_pong = false;
+    let result = loop {
+        tokio::select! {
+            message = outgoing.recv() => {
+                // every transport was dropped
+                let Some(message) = message else {
+                    break Ok(());
+                };
+                let text = match serde_json::to_string(&message) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        warn!("Failed to serialize message: {e}");
+                        continue;
+                    }
+                };
+                if let Err(e) = sink.send(Frame::text(text)).await {
+                    break Err(connection_closed(e.to_string(), None));
+                }
+            }
+            frame = stream.next() => match frame {
+                Some(Ok(Frame::Text(text))) => {
+                    let _ = incoming.send(parse_message(&text));
+                }
+                Some(Ok(Frame::Binary(_))) => warn!("Ignoring binary frame"),
+                Some(Ok(Frame::Pong(_))) => awaiting_pong = false,
+                Some(Ok(Frame::Close(frame))) => break close_result(frame),
+                // pings are answered by tungstenite
+                Some(Ok(_)) => {}
+                Some(Err(e)) => break Err(connection_closed(e.to_string(), None)),
+                None => break Ok(()),
+            },
+            _ = tick(&mut ping) => {
+                if awaiting_pong {
+                    break Err(connection_closed("No pong received".to_string(), None));
+                }
+                awaiting_pong = true;
+                if let Err(e) = sink.send(Frame::Ping(Default::default())).await {
+                    break Err(connection_closed(e.to_string(), None));
+                }
+            }
+            _ = closed.cancelled() => {
+                let frame = CloseFrame {
+                    code: CloseCode::Normal,
+                    reason: Default::default(),
+                };
+                let _ = sink.send(Frame::Close(Some(frame))).await;
+                break Ok(());
+            }
+        }
+    };
+    // flushes the reply to a close frame of the peer
+    let _ = sink.close().await;
+    if let Err(e) = result {
+        debug!("WebSocket closed: {e}");
+        let _ = incoming.send(Err(e.into()));
+    }
+}
+
+async fn tick(ping: &mut Option<Interval>) {
+    match ping {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Closing normally or going away ends the connection, other codes are errors
+fn close_result(frame: Option<CloseFrame>) -> std::result::Result<(), McpError> {
+    match frame {
+        None => Ok(()),
+        Some(frame) if matches!(frame.code, CloseCode::Normal | CloseCode::Away) => Ok(()),
+        Some(frame) => Err(connection_closed(
+            format!("closed with {}: {}", frame.code, frame.reason),
+            Some(serde_json::json!({
+                "closeCode": u16::from(frame.code),
+                "reason": frame.reason.as_str(),
+            })),
+        )),
+    }
+}
+
+fn connection_closed(reason: String, data: Option<serde_json::Value>) -> McpError {
+    let error = McpError::rpc(
+        ErrorCode::ConnectionClosed,
+        format!("WebSocket connection lost: {reason}"),
+    );
+    match data {
+        Some(data) => error.with_data(data),
+        None => error,
+    }
+}
+
+/// Accepts WebSocket connections, each one handed out as a transport by [`accept`]
+///
+/// [`accept`]: WebSocketServer::accept
+pub struct WebSocketServer {
+    connections: Mutex<mpsc::UnboundedReceiver<WebSocketTransport>>,
+    local_addr: SocketAddr,
+    task: AbortHandle,
+}
+
+impl WebSocketServer {
+    /// Listen on `addr`, the handshake of each connection runs in its own task
+    pub async fn bind(
+        addr: impl tokio::net::ToSocketAddrs,
+        options: WebSocketOptions,
+    ) -> Result<Self> {
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let (accept_tx, accept_rx) = mpsc::unbounded_channel();
+        let task = tokio::spawn(async move {
+            loop {
+                let (stream, peer) = match listener.accept().await {
+                    Ok(connection) => connection,
+                    Err(e) => {
+                        warn!("WebSocket server failed: {e}");
+                        break;
+                    }
+                };
+                let accept_tx = accept_tx.clone();
+                let options = options.clone();
+                tokio::spawn(async move {
+                    match tokio_tungstenite::accept_async(stream).await {
+                        Ok(ws) => {
+                            debug!("WebSocket connection from {peer}");
+                            let _ = accept_tx.send(WebSocketTransport::from_stream(ws, options));
+                        }
+                        Err(e) => debug!("WebSocket handshake with {peer} failed: {e}"),
+                    }
+                });
+            }
+        })
+        .abort_handle();
+        debug!("WebSocket server listening on {local_addr}");
+        Ok(Self {
+            connections: Mutex::new(accept_rx),
+            local_addr,
+            task,
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Wait for the next client to connect
+    pub async fn accept(&self) -> Option<WebSocketTransport> {
+        self.connections.lock().await.recv().await
+    }
+}
+
+impl Drop for WebSocketServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Client;
+    use crate::server::Server;
+    use crate::types::{Implementation, Root};
+    use url::Url;
+
+    fn url(server: &WebSocketServer) -> String {
+        format!("ws://{}", server.local_addr())
+    }
+
+    #[tokio::test]
+    async fn test_client_over_websocket() -> Result<()> {
+        let options = WebSocketOptions::default().ping_interval(Some(Duration::from_millis(50)));
+        let ws = WebSocketServer::bind("127.0.0.1:0", options.clone()).await?;
+        let transport = WebSocketTransport::connect(&url(&ws), options).await?;
+        let session = ws.accept().await.unwrap();
+        let server = Server::builder(session.clone()).build();
+        tokio::spawn({
+            let server = server.clone();
+            async move { server.listen().await }
+        });
+
+        let root = Root {
+            uri: Url::parse("file:///workspace")?,
+            name: None,
+        };
+        let client = Client::builder(transport.clone())
+            .roots(vec![root.clone()])
+            .build();
+        tokio::spawn({
+            let client = client.clone();
+            async move { client.start().await }
+        });
+        client.initialize(Implementation::default()).await?;
+        // both sides answer pings while idle
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        client.ping().await?;
+        assert_eq!(server.list_roots().await?, vec![root]);
+
+        transport.close().await?;
+        assert!(session.receive().await?.is_none());
+        Ok(())
+    }
+
+    /// Accept one raw WebSocket connection and hand it to `handle`
+    async fn raw_server<F>(
+        handle: impl FnOnce(WebSocketStream<tokio::net::TcpStream>) -> F + Send + 'static,
+    ) -> Result<String>
+    where
+        F: std::future::Future<Output = ()> + Send,
+    {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("ws://{}", listener.local_addr()?);
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            handle(tokio_tungstenite::accept_async(stream).await.unwrap()).await;
+        });
+        Ok(url)
+    }
+
+    fn error_code(e: anyhow::Error) -> i32 {
+        McpError::from(e).code()
+    }
+
+    #[tokio::test]
+    async fn test_close_code_maps_to_connection_closed() -> Result<()> {
+        let url = raw_server(|mut ws| async move {
+            let frame = CloseFrame {
+                code: CloseCode::Error,
+                reason: "boom".into(),
+            };
+            ws.close(Some(frame)).await.unwrap();
+        })
+        .await?;
+        let transport = WebSocketTransport::connect(&url, Default::default()).await?;
+        let client = Client::builder(transport).build();
+        let e = client.start().await.unwrap_err();
+        assert_eq!(e.code(), ErrorCode::ConnectionClosed as i32);
+
+        let url = raw_server(|mut ws| async move {
+            ws.close(None).await.unwrap();
+        })
+        .await?;
+        let transport = WebSocketTransport::connect(&url, Default::default()).await?;
+        assert!(transport.receive().await?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_missing_pong_closes_connection() -> Result<()> {
+        // never reads, so pings are not answered
+        let url = raw_server(|ws| async move {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            drop(ws);
+        })
+        .await?;
+        let options = WebSocketOptions::default().ping_interval(Some(Duration::from_millis(20)));
+        let transport = WebSocketTransport::connect(&url, options).await?;
+        let e = transport.receive().await.unwrap_err();
+        assert_eq!(error_code(e), ErrorCode::ConnectionClosed as i32);
+        Ok(())
+    }
+}